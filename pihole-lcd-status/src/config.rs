@@ -0,0 +1,472 @@
+use rustberrypi::i2c::lcd::Geometry;
+
+use serde_derive::Deserialize;
+use structopt::StructOpt;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::PiHoleError;
+
+/// Options given on the command line, or with environment variables. They
+/// take precedence over the config file.
+#[derive(StructOpt, Debug, Default)]
+#[structopt(
+    name = "pihole-lcd-status",
+    about = "Shows the statistics of Pi-hole on a LCD plate."
+)]
+struct Options {
+    /// TOML file to read the settings from, the options below taking precedence
+    #[structopt(
+        long = "config",
+        value_name = "PATH",
+        env = "PIHOLE_LCD_CONFIG",
+        parse(from_os_str)
+    )]
+    config: Option<PathBuf>,
+    /// URL of the web interface of Pi-hole, or of admin/api.php, repeated or separated by commas
+    /// for several instances [default: http://localhost]
+    #[structopt(
+        long = "endpoint",
+        value_name = "URL",
+        env = "PIHOLE_ENDPOINT",
+        raw(number_of_values = "1", use_delimiter = "true")
+    )]
+    endpoints: Vec<String>,
+    /// API token of Pi-hole v5, or password of v6, needed to turn blocking off, for each instance
+    #[structopt(
+        long = "token",
+        value_name = "TOKEN",
+        env = "PIHOLE_TOKEN",
+        raw(hide_env_values = "true")
+    )]
+    token: Option<String>,
+    /// Seconds to wait for Pi-hole to answer [default: 5]
+    #[structopt(
+        long = "timeout",
+        value_name = "SECONDS",
+        env = "PIHOLE_TIMEOUT",
+        parse(try_from_str = "parse_seconds")
+    )]
+    timeout: Option<Duration>,
+    /// Seconds between two fetches of the statistics [default: 30]
+    #[structopt(
+        long = "poll-interval",
+        value_name = "SECONDS",
+        env = "PIHOLE_POLL_INTERVAL",
+        parse(try_from_str = "parse_seconds")
+    )]
+    poll_interval: Option<Duration>,
+    /// Seconds without statistics before Pi-hole is shown as unreachable [default: 120]
+    #[structopt(
+        long = "offline-after",
+        value_name = "SECONDS",
+        env = "PIHOLE_OFFLINE_AFTER",
+        parse(try_from_str = "parse_seconds")
+    )]
+    offline_after: Option<Duration>,
+    /// How long a screen is shown, as SCREEN=SECONDS, e.g. history=20
+    #[structopt(
+        long = "duration",
+        value_name = "SCREEN=SECONDS",
+        raw(number_of_values = "1"),
+        parse(try_from_str = "parse_screen_duration")
+    )]
+    durations: Vec<(String, Duration)>,
+    /// Size of the panel, as COLSxLINES [default: 16x2]
+    #[structopt(
+        long = "size",
+        value_name = "COLSxLINES",
+        parse(try_from_str = "parse_size")
+    )]
+    size: Option<Geometry>,
+    /// BCM number of the GPIO wired to the INTA/INTB line of the plate, if any
+    #[structopt(long = "interrupt-pin", value_name = "GPIO")]
+    interrupt_pin: Option<u8>,
+    /// Draw the plate in the terminal, instead of looking for a LCD plate
+    #[structopt(long = "terminal")]
+    terminal: bool,
+}
+
+/// Settings read from the config file, named like the command line options.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct File {
+    endpoint: Option<String>,
+    token: Option<String>,
+    /// Instead of the endpoint, for several instances.
+    #[serde(default)]
+    instances: Vec<FileInstance>,
+    timeout: Option<u64>,
+    poll_interval: Option<u64>,
+    offline_after: Option<u64>,
+    #[serde(default)]
+    durations: BTreeMap<String, u64>,
+    size: Option<String>,
+    interrupt_pin: Option<u8>,
+}
+
+/// An instance of the config file, using the token of the file when it
+/// doesn't have one.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FileInstance {
+    name: Option<String>,
+    endpoint: String,
+    token: Option<String>,
+}
+
+/// How long each screen is shown, in the carousel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Durations {
+    pub countdown: Duration,
+    pub ferris: Duration,
+    pub queries: Duration,
+    pub blocked: Duration,
+    pub summary: Duration,
+    pub history: Duration,
+    /// Of the page of each instance, when there are several.
+    pub instance: Duration,
+    pub cache: Duration,
+    pub upstreams: Duration,
+    pub query_types: Duration,
+    pub top_blocked: Duration,
+    pub top_domains: Duration,
+    pub top_clients: Duration,
+    pub tail: Duration,
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Durations {
+            countdown: Duration::from_secs(10),
+            ferris: Duration::from_secs(3),
+            queries: Duration::from_secs(10),
+            blocked: Duration::from_secs(10),
+            summary: Duration::from_secs(20),
+            history: Duration::from_secs(10),
+            instance: Duration::from_secs(10),
+            cache: Duration::from_secs(10),
+            upstreams: Duration::from_secs(10),
+            query_types: Duration::from_secs(10),
+            top_blocked: Duration::from_secs(15),
+            top_domains: Duration::from_secs(15),
+            top_clients: Duration::from_secs(15),
+            tail: Duration::from_secs(20),
+        }
+    }
+}
+
+impl Durations {
+    const SCREENS: &'static str = "countdown, ferris, queries, blocked, summary, instance, \
+                                   history, cache, upstreams, query_types, top_blocked, \
+                                   top_domains, top_clients, tail";
+
+    fn set(&mut self, screen: &str, duration: Duration) -> Result<(), String> {
+        let field = match screen {
+            "countdown" => &mut self.countdown,
+            "ferris" => &mut self.ferris,
+            "queries" => &mut self.queries,
+            "blocked" => &mut self.blocked,
+            "summary" => &mut self.summary,
+            "history" => &mut self.history,
+            "instance" => &mut self.instance,
+            "cache" => &mut self.cache,
+            "upstreams" => &mut self.upstreams,
+            "query_types" => &mut self.query_types,
+            "top_blocked" => &mut self.top_blocked,
+            "top_domains" => &mut self.top_domains,
+            "top_clients" => &mut self.top_clients,
+            "tail" => &mut self.tail,
+            _ => {
+                return Err(format!(
+                    "unknown screen \"{}\", expected one of {}",
+                    screen,
+                    Self::SCREENS
+                ))
+            }
+        };
+        *field = duration;
+        Ok(())
+    }
+}
+
+/// One of the Pi-hole instances polled, e.g. a primary and a secondary one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    /// Shown on its page, the host of the endpoint unless named in the config file.
+    pub name: String,
+    pub endpoint: String,
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub instances: Vec<Instance>,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    /// How long the statistics can be stale before Pi-hole is shown as unreachable.
+    pub offline_after: Duration,
+    pub durations: Durations,
+    pub geometry: Geometry,
+    pub interrupt_pin: Option<u8>,
+    pub terminal: bool,
+}
+
+impl Config {
+    /// Read the command line, the environment and the config file, if any.
+    /// Invalid options make the program exit with a usage message.
+    pub fn load() -> Result<Config, PiHoleError> {
+        let options = Options::from_args();
+        let file = match options.config {
+            Some(ref path) => read_file(path)?,
+            None => File::default(),
+        };
+        Config::merge(options, file).map_err(PiHoleError::ConfigError)
+    }
+
+    fn merge(options: Options, file: File) -> Result<Config, String> {
+        let seconds = |name: &str, secs: u64| match secs {
+            0 => Err(format!("{} must be at least 1 second", name)),
+            secs => Ok(Duration::from_secs(secs)),
+        };
+
+        if file.endpoint.is_some() && !file.instances.is_empty() {
+            return Err("either endpoint or instances can be set, not both".to_string());
+        }
+        let file_token = file.token;
+        let listed: Vec<(Option<String>, String, Option<String>)> =
+            match (options.endpoints, file.endpoint) {
+                (endpoints, _) if !endpoints.is_empty() => endpoints
+                    .into_iter()
+                    .map(|endpoint| (None, endpoint, None))
+                    .collect(),
+                (_, Some(endpoint)) => vec![(None, endpoint, None)],
+                (_, None) if !file.instances.is_empty() => file
+                    .instances
+                    .into_iter()
+                    .map(|instance| (instance.name, instance.endpoint, instance.token))
+                    .collect(),
+                (_, None) => vec![(None, "http://localhost".to_string(), None)],
+            };
+        let mut instances = vec![];
+        for (name, endpoint, token) in listed {
+            // Only plain HTTP is supported, Pi-hole being on the local network.
+            let host = match endpoint.strip_prefix("http://") {
+                Some(rest) => rest.split('/').next().unwrap_or(rest).to_string(),
+                None => {
+                    return Err(format!(
+                        "endpoint must be an http:// URL, e.g. http://pi.hole, got \"{}\"",
+                        endpoint
+                    ))
+                }
+            };
+            let token = options.token.clone().or(token).or_else(|| file_token.clone());
+            instances.push(Instance {
+                name: name.unwrap_or(host),
+                endpoint,
+                token: token.filter(|token| !token.is_empty()),
+            });
+        }
+
+        let mut durations = Durations::default();
+        for (screen, secs) in file.durations {
+            let duration = seconds(&format!("the duration of {}", screen), secs)?;
+            durations.set(&screen, duration)?;
+        }
+        for (screen, duration) in options.durations {
+            durations.set(&screen, duration)?;
+        }
+
+        let geometry = match (options.size, file.size) {
+            (Some(geometry), _) => geometry,
+            (None, Some(size)) => parse_size(&size)?,
+            (None, None) => Geometry::LCD_16X2,
+        };
+
+        Ok(Config {
+            instances,
+            timeout: match (options.timeout, file.timeout) {
+                (Some(timeout), _) => timeout,
+                (None, Some(secs)) => seconds("timeout", secs)?,
+                (None, None) => Duration::from_secs(5),
+            },
+            poll_interval: match (options.poll_interval, file.poll_interval) {
+                (Some(interval), _) => interval,
+                (None, Some(secs)) => seconds("poll_interval", secs)?,
+                (None, None) => Duration::from_secs(30),
+            },
+            offline_after: match (options.offline_after, file.offline_after) {
+                (Some(after), _) => after,
+                (None, Some(secs)) => seconds("offline_after", secs)?,
+                (None, None) => Duration::from_secs(120),
+            },
+            durations,
+            geometry,
+            interrupt_pin: options.interrupt_pin.or(file.interrupt_pin),
+            terminal: options.terminal,
+        })
+    }
+}
+
+fn read_file(path: &Path) -> Result<File, PiHoleError> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        PiHoleError::ConfigError(format!("could not read {}: {}", path.display(), e))
+    })?;
+    toml::from_str(&text)
+        .map_err(|e| PiHoleError::ConfigError(format!("invalid {}: {}", path.display(), e)))
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    match text.parse::<u64>() {
+        Ok(0) => Err("expects at least 1 second".to_string()),
+        Ok(secs) => Ok(Duration::from_secs(secs)),
+        Err(_) => Err(format!("expects a number of seconds, got \"{}\"", text)),
+    }
+}
+
+fn parse_screen_duration(text: &str) -> Result<(String, Duration), String> {
+    let (screen, secs) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expects SCREEN=SECONDS, e.g. history=20, got \"{}\"", text))?;
+    // Checked here as well, so that the error is reported along with the option.
+    Durations::default().set(screen, Duration::from_secs(1))?;
+    Ok((screen.to_string(), parse_seconds(secs)?))
+}
+
+fn parse_size(text: &str) -> Result<Geometry, String> {
+    let size = text
+        .split_once('x')
+        .and_then(|(cols, lines)| Some((cols.parse::<u8>().ok()?, lines.parse::<u8>().ok()?)));
+    match size {
+        // Which also rules out the 4 lines panels wider than 20 columns.
+        Some((cols @ 1..=80, lines @ 1..=4)) if u16::from(cols) * u16::from(lines) > 80 => {
+            Err(format!(
+                "a {}x{} panel has more characters than the 80 a single HD44780 \
+                 controller can address",
+                cols, lines
+            ))
+        }
+        Some((cols @ 1..=80, lines @ 1..=4)) => Ok(Geometry::new(cols, lines)),
+        _ => Err(format!(
+            "expects the size of the panel as COLSxLINES, e.g. 20x4, got \"{}\"",
+            text
+        )),
+    }
+}
+
+#[test]
+fn merge() {
+    let file: File = toml::from_str(
+        r#"
+        endpoint = "http://pi.hole/admin/api.php"
+        timeout = 2
+        size = "20x4"
+
+        [durations]
+        history = 30
+        "#,
+    )
+    .unwrap();
+    let options = Options {
+        timeout: Some(Duration::from_secs(10)),
+        durations: vec![("ferris".to_string(), Duration::from_secs(1))],
+        ..Options::default()
+    };
+    let config = Config::merge(options, file).unwrap();
+    assert_eq!(config.instances[0].endpoint, "http://pi.hole/admin/api.php");
+    assert_eq!(config.instances[0].name, "pi.hole");
+    assert_eq!(config.timeout, Duration::from_secs(10));
+    assert_eq!(config.poll_interval, Duration::from_secs(30));
+    assert_eq!(config.durations.history, Duration::from_secs(30));
+    assert_eq!(config.durations.ferris, Duration::from_secs(1));
+    assert_eq!(config.geometry, Geometry::LCD_20X4);
+    assert_eq!(config.instances[0].token, None);
+}
+
+#[test]
+fn instances() {
+    let file: File = toml::from_str(
+        r#"
+        token = "shared"
+
+        [[instances]]
+        name = "primary"
+        endpoint = "http://192.168.1.2"
+
+        [[instances]]
+        endpoint = "http://192.168.1.3:8080/admin/api.php"
+        token = "own"
+        "#,
+    )
+    .unwrap();
+    let config = Config::merge(Options::default(), file).unwrap();
+    let instances: Vec<(&str, Option<&str>)> = config
+        .instances
+        .iter()
+        .map(|instance| (&instance.name[..], instance.token.as_deref()))
+        .collect();
+    assert_eq!(
+        instances,
+        vec![("primary", Some("shared")), ("192.168.1.3:8080", Some("own"))]
+    );
+
+    // Endpoints given as options replace the instances of the file.
+    let options = Options::from_iter_safe(vec![
+        "pihole-lcd-status",
+        "--endpoint",
+        "http://pi.hole,http://backup.lan",
+        "--token",
+        "secret",
+    ])
+    .unwrap();
+    let config = Config::merge(options, File::default()).unwrap();
+    assert_eq!(config.instances.len(), 2);
+    assert_eq!(config.instances[1].name, "backup.lan");
+    assert_eq!(config.instances[1].token.as_deref(), Some("secret"));
+
+    let both = "endpoint = \"http://pi.hole\"\n[[instances]]\nendpoint = \"http://backup.lan\"";
+    assert!(Config::merge(Options::default(), toml::from_str(both).unwrap()).is_err());
+}
+
+#[test]
+fn errors() {
+    let merge = |text: &str| Config::merge(Options::default(), toml::from_str(text).unwrap());
+    assert_eq!(
+        merge("poll_interval = 0"),
+        Err("poll_interval must be at least 1 second".to_string())
+    );
+    assert_eq!(
+        merge("[durations]\nsparkline = 5"),
+        Err(format!(
+            "unknown screen \"sparkline\", expected one of {}",
+            Durations::SCREENS
+        ))
+    );
+    assert!(merge("endpoint = \"pi.hole\"")
+        .unwrap_err()
+        .contains("http://"));
+    assert!(toml::from_str::<File>("poll = 5").is_err());
+
+    assert_eq!(
+        parse_seconds("5s"),
+        Err("expects a number of seconds, got \"5s\"".to_string())
+    );
+    assert!(parse_size("20x5").is_err());
+    assert_eq!(parse_size("40x2").map(|size| size.cols()), Ok(40));
+    assert_eq!(
+        parse_size("40x4").map(|_| ()),
+        Err(
+            "a 40x4 panel has more characters than the 80 a single HD44780 controller can \
+             address"
+                .to_string()
+        )
+    );
+    assert!(parse_size("80x2").is_err());
+    assert!(parse_size("21x4").is_err());
+    assert_eq!(
+        parse_screen_duration("history=20"),
+        Ok(("history".to_string(), Duration::from_secs(20)))
+    );
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+
+use crate::PiHoleError;
+
+/// How fresh the statistics shown are, from the outcome of the last fetches.
+/// They are stale once a fetch failed, until one succeeds again, and
+/// Pi-hole is deemed unreachable when they've been stale for too long.
+pub struct Health {
+    started_at: Instant,
+    /// When the statistics shown were fetched, if they ever were.
+    fetched_at: Option<Instant>,
+    /// Why the last fetch failed, `None` once one succeeded.
+    error: Option<PiHoleError>,
+    offline_after: Duration,
+}
+
+impl Health {
+    pub fn new(offline_after: Duration, now: Instant) -> Self {
+        Health {
+            started_at: now,
+            fetched_at: None,
+            error: None,
+            offline_after,
+        }
+    }
+
+    pub fn fetched(&mut self, at: Instant) {
+        self.fetched_at = Some(at);
+        self.error = None;
+    }
+
+    pub fn failed(&mut self, error: PiHoleError) {
+        self.error = Some(error);
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Whether nothing could be fetched for `offline_after`, counting from
+    /// the start when nothing ever was.
+    pub fn is_unreachable(&self, now: Instant) -> bool {
+        let since = self.fetched_at.unwrap_or(self.started_at);
+        self.is_stale() && now >= since + self.offline_after
+    }
+
+    /// The age of the statistics, while they're stale.
+    pub fn stale_for(&self, now: Instant) -> Option<Duration> {
+        let fetched_at = self.fetched_at.filter(|_| self.is_stale())?;
+        Some(now.saturating_duration_since(fetched_at))
+    }
+
+    /// The age of the statistics, shown over the screens while they're
+    /// stale, e.g. `!5m`.
+    pub fn marker(&self, now: Instant) -> Option<String> {
+        let secs = self.stale_for(now)?.as_secs();
+        Some(match secs {
+            0..=59 => format!("!{}s", secs),
+            60..=3599 => format!("!{}m", secs / 60),
+            _ => format!("!{}h", secs / 3600),
+        })
+    }
+}
+
+/// Delays between the attempts to fetch the statistics after a failure,
+/// doubling each time up to a maximum, so that an unreachable Pi-hole is
+/// retried quickly at first without being flooded afterwards.
+pub struct Backoff {
+    first: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(first: Duration, max: Duration) -> Self {
+        Backoff {
+            first,
+            max,
+            next: first,
+        }
+    }
+
+    /// How long to wait after another failure.
+    pub fn delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Start over from the first delay, once a fetch succeeded.
+    pub fn reset(&mut self) {
+        self.next = self.first;
+    }
+}
+
+#[test]
+fn staleness() {
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+    let mut health = Health::new(Duration::from_secs(120), start);
+    health.failed(PiHoleError::Unauthorized);
+    assert!(health.is_stale());
+    assert_eq!(health.marker(at(10)), None);
+    assert!(health.is_unreachable(at(120)));
+
+    health.fetched(at(130));
+    assert!(!health.is_unreachable(at(200)));
+    assert_eq!(health.marker(at(200)), None);
+    health.failed(PiHoleError::Unauthorized);
+    assert_eq!(health.marker(at(175)).as_deref(), Some("!45s"));
+    assert_eq!(health.marker(at(250)).as_deref(), Some("!2m"));
+    assert!(!health.is_unreachable(at(249)));
+    assert!(health.is_unreachable(at(250)));
+}
+
+#[test]
+fn backoff() {
+    let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(10));
+    let delays: Vec<u64> = (0..5).map(|_| backoff.delay().as_secs()).collect();
+    assert_eq!(delays, vec![2, 4, 8, 10, 10]);
+    backoff.reset();
+    assert_eq!(backoff.delay(), Duration::from_secs(2));
+}
@@ -0,0 +1,159 @@
+//! The Pi-hole instances polled, e.g. a primary and a secondary one, and
+//! their statistics combined into the ones shown.
+
+use std::time::{Duration, Instant};
+
+use crate::health::Health;
+use crate::pihole::{PiHoleHistory, PiHoleShares, PiHoleStatus, PiHoleTop};
+use crate::{PiHoleError, Stats};
+
+/// How an instance is doing, as shown on its page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceStatus {
+    pub name: String,
+    /// From the last statistics fetched, if any ever were.
+    pub status: Option<PiHoleStatus>,
+    /// Whether the last fetch failed.
+    pub down: bool,
+}
+
+/// One of the instances, and its statistics as last fetched.
+struct Instance {
+    name: String,
+    health: Health,
+    /// The last statistics fetched, kept while the instance is down.
+    stats: Option<Stats>,
+}
+
+/// The instances, in the order of the config, each fetched on its own so
+/// that the others are still shown while one is down.
+pub struct Instances {
+    instances: Vec<Instance>,
+}
+
+impl Instances {
+    pub fn new(names: Vec<String>, offline_after: Duration, now: Instant) -> Self {
+        let instances = names
+            .into_iter()
+            .map(|name| Instance {
+                name,
+                health: Health::new(offline_after, now),
+                stats: None,
+            })
+            .collect();
+        Instances { instances }
+    }
+
+    pub fn fetched(&mut self, index: usize, stats: Stats) {
+        let instance = &mut self.instances[index];
+        instance.health.fetched(stats.fetched_at);
+        instance.stats = Some(stats);
+    }
+
+    pub fn failed(&mut self, index: usize, error: PiHoleError) {
+        self.instances[index].health.failed(error);
+    }
+
+    /// Whether the statistics of an instance are stale.
+    pub fn is_stale(&self) -> bool {
+        self.instances
+            .iter()
+            .any(|instance| instance.health.is_stale())
+    }
+
+    /// Whether none of the instances could be reached for a while.
+    pub fn is_unreachable(&self, now: Instant) -> bool {
+        self.instances
+            .iter()
+            .all(|instance| instance.health.is_unreachable(now))
+    }
+
+    /// The age of the stalest statistics, e.g. `!5m`.
+    pub fn marker(&self, now: Instant) -> Option<String> {
+        let stalest = self
+            .instances
+            .iter()
+            .max_by_key(|instance| instance.health.stale_for(now))?;
+        stalest.health.marker(now)
+    }
+
+    /// Whether blocking was last seen off on an instance.
+    pub fn is_disabled(&self) -> bool {
+        self.instances
+            .iter()
+            .filter_map(|instance| instance.stats.as_ref())
+            .any(|stats| !stats.status.blocking)
+    }
+
+    /// The last statistics of all the instances added up, along with how
+    /// each instance is doing, once any were fetched.
+    pub fn combined(&self) -> Option<Stats> {
+        let fetched: Vec<&Stats> = self
+            .instances
+            .iter()
+            .filter_map(|instance| instance.stats.as_ref())
+            .collect();
+        let fetched_at = fetched.iter().map(|stats| stats.fetched_at).max()?;
+        let histories: Vec<_> = fetched.iter().filter_map(|s| s.history.as_ref()).collect();
+        let tops: Vec<_> = fetched.iter().filter_map(|s| s.top.as_ref()).collect();
+        let shares: Vec<_> = fetched
+            .iter()
+            .filter_map(|s| Some((s.shares.as_ref()?, &s.status)))
+            .collect();
+        let instances = self
+            .instances
+            .iter()
+            .map(|instance| InstanceStatus {
+                name: instance.name.clone(),
+                status: instance.stats.as_ref().map(|stats| stats.status.clone()),
+                down: instance.health.is_stale(),
+            })
+            .collect();
+        Some(Stats {
+            status: PiHoleStatus::combined(fetched.iter().map(|stats| &stats.status)),
+            history: Some(PiHoleHistory::combined(histories.iter().cloned()))
+                .filter(|_| !histories.is_empty()),
+            top: Some(PiHoleTop::combined(tops.iter().cloned())).filter(|_| !tops.is_empty()),
+            shares: Some(PiHoleShares::combined(shares.iter().cloned()))
+                .filter(|_| !shares.is_empty()),
+            instances,
+            fetched_at,
+        })
+    }
+}
+
+#[test]
+fn one_down() {
+    let now = Instant::now();
+    let names = vec!["primary".to_string(), "secondary".to_string()];
+    let mut instances = Instances::new(names, Duration::from_secs(120), now);
+    assert!(instances.combined().is_none());
+
+    let stats = |queries: usize, blocking: bool| Stats {
+        status: PiHoleStatus {
+            dns_queries_today: queries,
+            blocking,
+            ..PiHoleStatus::default()
+        },
+        fetched_at: now,
+        ..Stats::default()
+    };
+    instances.fetched(0, stats(3000, true));
+    instances.failed(1, PiHoleError::Unauthorized);
+    let combined = instances.combined().unwrap();
+    assert_eq!(combined.status.dns_queries_today, 3000);
+    assert!(combined.instances[1].down && combined.instances[1].status.is_none());
+    assert!(!instances.is_unreachable(now + Duration::from_secs(120)));
+    assert!(!instances.is_disabled());
+
+    instances.fetched(1, stats(1000, false));
+    instances.failed(0, PiHoleError::Unauthorized);
+    let combined = instances.combined().unwrap();
+    assert_eq!(combined.status.dns_queries_today, 4000);
+    assert!(combined.instances[0].down);
+    assert!(instances.is_disabled());
+    assert_eq!(
+        instances.marker(now + Duration::from_secs(90)).as_deref(),
+        Some("!1m")
+    );
+}
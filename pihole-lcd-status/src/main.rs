@@ -1,135 +1,606 @@
 use rustberrypi::errors::CommunicationError;
-use rustberrypi::i2c::lcd;
-use rustberrypi::i2c::lcd::AdafruitDisplay;
-use rustberrypi::i2c::lcd::Button;
+use rustberrypi::i2c::bus::I2cBus;
+use rustberrypi::i2c::lcd::buttons::{
+    AutoRepeat, ButtonConfig, ButtonEvent, ButtonEvents, InterruptLine,
+};
+use rustberrypi::i2c::io::MCP230xx;
+use rustberrypi::i2c::lcd::layout;
+use rustberrypi::i2c::lcd::{AdafruitDisplay, DisplayBuilder, Frame, Geometry};
+use rustberrypi::i2c::lcd::{Button, Item, Menu, MenuEvent};
 
-use serde_derive::Deserialize;
-use ureq;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
 
-use std::char;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::thread;
+use std::time::{Duration, Instant};
 
-fn display_ferris(display: &mut AdafruitDisplay) -> Result<(), CommunicationError> {
-    lcd::helpers::load_ferris(display)?;
+mod config;
+mod health;
+mod instances;
+mod pihole;
+mod screen;
+mod screens;
+mod terminal;
 
-    display.clear()?;
-    display.home()?;
+use crate::config::{Config, Durations};
+use crate::health::Backoff;
+use crate::instances::{InstanceStatus, Instances};
+use crate::pihole::{
+    PiHole, PiHoleHistory, PiHoleQuery, PiHoleShares, PiHoleStatus, PiHoleTop, QueryCursor,
+};
+use crate::screen::{Carousel, Screen};
+use crate::screens::{ShareList, TopList};
 
-    display.message(&format!(
-        "{}{}{}{} Pi-hole",
-        char::from_u32(0).unwrap(),
-        char::from_u32(1).unwrap(),
-        char::from_u32(2).unwrap(),
-        char::from_u32(3).unwrap()
-    ))?;
+/// How often the buttons are read when their interrupt line isn't wired.
+const BUTTON_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Up and Down repeat while held, to scroll the menu and step its values.
+const REPEAT: AutoRepeat = AutoRepeat {
+    delay: Duration::from_millis(500),
+    interval: Duration::from_millis(150),
+};
+/// The menu closes after this long without a button being pressed.
+const MENU_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long messages are shown over the screens.
+const OVERLAY_DURATION: Duration = Duration::from_secs(5);
+/// Color of the backlight while blocking is disabled.
+const DISABLED_COLOR: (u8, u8, u8) = (255, 255, 0);
+/// Color of the backlight while Pi-hole is unreachable.
+const UNREACHABLE_COLOR: (u8, u8, u8) = (255, 0, 0);
+/// Delays before fetching the statistics again after a failure.
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+const MAX_RETRY: Duration = Duration::from_secs(120);
+/// Number of entries fetched for each top list.
+const TOP_COUNT: usize = 10;
+/// How often the query log is read, and how many of the last queries.
+const TAIL_INTERVAL: Duration = Duration::from_secs(2);
+const TAIL_COUNT: usize = 20;
 
-    display.set_cursor(0, 1)?;
-    display.message(&format!(
-        "{}{}{}{} Status",
-        char::from_u32(4).unwrap(),
-        char::from_u32(5).unwrap(),
-        char::from_u32(6).unwrap(),
-        char::from_u32(7).unwrap()
-    ))?;
+fn main() -> Result<(), PiHoleError> {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(PiHoleError::ConfigError(message)) => {
+            eprintln!("error: {}", message);
+            std::process::exit(1);
+        }
+        Err(e) => return Err(e),
+    };
 
-    Ok(())
+    let (tx, rx) = mpsc::channel();
+    // The terminal panel can be forced, or used when no LCD plate is connected.
+    if !config.terminal {
+        let display = MCP230xx::for_mcp23017()
+            .and_then(|gpio| DisplayBuilder::new(config.geometry).build(gpio));
+        match display {
+            Ok(display) => return run(display, config.interrupt_pin, &config, tx, rx),
+            Err(e) if !terminal::is_available() => return Err(e.into()),
+            Err(e) => eprintln!(
+                "Could not set up the LCD plate ({:?}), using the terminal.",
+                e
+            ),
+        }
+    }
+    // Quitting from the terminal goes through the main loop, like Ctrl-C.
+    let quit_tx = tx.clone();
+    let display = terminal::start(config.geometry, move || {
+        let _ = quit_tx.send(Event::Shutdown);
+    })?;
+    let result = run(display, None, &config, tx, rx);
+    terminal::restore();
+    result
 }
 
-fn get_pihole_status() -> Result<PiHoleStatus, PiHoleError> {
-    let status: PiHoleStatus = serde_json::from_str(
-        &ureq::get("http://192.168.188.20/admin/api.php")
-            .call()
-            .into_string()?,
-    )?;
-    Ok(status)
+/// Tell the main loop whenever the plate raises an interrupt, waiting for
+/// it to read the buttons, which clears the interrupt, before watching again.
+fn watch_interrupts(mut line: InterruptLine, tx: Sender<Event>, read: Receiver<()>) {
+    loop {
+        let event = match line.wait(None) {
+            Ok(_) => Event::Interrupt,
+            Err(e) => Event::Stopped(e.into()),
+        };
+        if tx.send(event).is_err() || read.recv().is_err() {
+            return;
+        }
+    }
 }
 
-fn main() -> Result<(), PiHoleError> {
-    let display = Arc::new(Mutex::new(AdafruitDisplay::for_backplate()?));
-    let d = display.clone();
-    ctrlc::set_handler(move || {
-        &mut d
-            .lock()
-            .map_err(|_| panic!("Could not lock access to display."))
-            .unwrap()
-            .set_color(0, 0, 0);
-        std::process::exit(1);
-    }).expect("Error setting Ctrl-C handler");
-
-    let d1 = display.clone();
-    thread::spawn(move || loop {
-        let status: PiHoleStatus = get_pihole_status()
-            .map_err(|_| println!("Could not fetch stats for PiHole, will retry."))
-            .unwrap();
-        display_ferris(
-            &mut d1
-                .clone()
-                .lock()
-                .map_err(|_| panic!("Could not lock access to display."))
-                .unwrap(),
-        ).unwrap();
-        std::thread::sleep(std::time::Duration::from_secs(3));
+/// Fetch the statistics of an instance periodically, and sooner after a
+/// failure, reporting them along with the index of the instance.
+fn poll_pihole(index: usize, pihole: PiHole, interval: Duration, tx: Sender<Event>) {
+    let mut backoff = Backoff::new(FIRST_RETRY, MAX_RETRY.max(interval));
+    loop {
+        let (event, delay) = match pihole.status() {
+            Ok(status) => {
+                let name = pihole.name();
+                let history = pihole
+                    .history()
+                    .map_err(|e| println!("Could not fetch the history of {}: {}", name, e))
+                    .ok();
+                // Pi-hole v5 only shares the top lists with the API token.
+                let top = match pihole.top(TOP_COUNT) {
+                    Ok(top) => Some(top),
+                    Err(PiHoleError::Unauthorized) => None,
+                    Err(e) => {
+                        println!("Could not fetch the top lists of {}: {}", name, e);
+                        None
+                    }
+                };
+                // Like the top lists, with the API token only.
+                let shares = match pihole.shares() {
+                    Ok(shares) => Some(shares),
+                    Err(PiHoleError::Unauthorized) => None,
+                    Err(e) => {
+                        println!("Could not fetch the split of the queries of {}: {}", name, e);
+                        None
+                    }
+                };
+                let fetched_at = Instant::now();
+                backoff.reset();
+                let stats = Stats {
+                    status,
+                    history,
+                    top,
+                    shares,
+                    instances: vec![],
+                    fetched_at,
+                };
+                (Event::Stats(index, Box::new(stats)), interval)
+            }
+            Err(e) => {
+                let delay = backoff.delay();
+                println!(
+                    "Could not fetch the stats of {}, retrying in {:?}: {}",
+                    pihole.name(),
+                    delay,
+                    e
+                );
+                (Event::Failed(index, e), delay)
+            }
+        };
+        if tx.send(event).is_err() {
+            return;
+        }
+        thread::sleep(delay);
+    }
+}
 
-        {
-            let display = &mut d1
-                .lock()
-                .map_err(|_| panic!("Could not lock access to display."))
-                .unwrap();
-            display.clear().unwrap();
-            display
-                .message(&format!(
-                    "DNS last 24h\n{} queries",
-                    status.dns_queries_today
-                )).unwrap();
+/// Follow the query log of an instance, for the tail screen. Failures are left
+/// to the poll of the statistics to report.
+fn tail_queries(pihole: PiHole, tx: Sender<Event>) {
+    let mut cursor = QueryCursor::default();
+    loop {
+        match pihole.queries(&mut cursor, TAIL_COUNT) {
+            Ok(queries) if queries.is_empty() => {}
+            Ok(queries) => {
+                if tx.send(Event::Queries(queries)).is_err() {
+                    return;
+                }
+            }
+            // Pi-hole v5 only shares the query log with the API token.
+            Err(PiHoleError::Unauthorized) => return,
+            Err(_) => {}
         }
+        thread::sleep(TAIL_INTERVAL);
+    }
+}
 
-        std::thread::sleep(std::time::Duration::from_secs(10));
+/// The screens shown in turn, depending on the size of the panel. The
+/// countdown until blocking is back on is shown first, while it is disabled.
+/// With several instances, their combined statistics are followed by a page
+/// for each of them.
+fn carousel<B: I2cBus>(
+    lines: u8,
+    durations: &Durations,
+    disabled_until: Rc<Cell<Option<Instant>>>,
+    instances: usize,
+) -> Carousel<B> {
+    let mut carousel = Carousel::new();
+    carousel.add(screens::Countdown::new(disabled_until), durations.countdown);
+    carousel.add(screens::Ferris, durations.ferris);
+    if lines >= 4 {
+        carousel.add(screens::Summary::default(), durations.summary);
+    } else {
+        carousel.add(screens::Queries::default(), durations.queries);
+        carousel.add(screens::Blocked::default(), durations.blocked);
+    }
+    if instances > 1 {
+        for index in 0..instances {
+            carousel.add(screens::Instance::new(index), durations.instance);
+        }
+    }
+    carousel.add(screens::History::default(), durations.history);
+    carousel.add(screens::Cache::default(), durations.cache);
+    carousel.add(screens::Shares::new(ShareList::Upstreams), durations.upstreams);
+    carousel.add(screens::Shares::new(ShareList::QueryTypes), durations.query_types);
+    carousel.add(screens::Top::new(TopList::Blocked), durations.top_blocked);
+    carousel.add(screens::Top::new(TopList::Permitted), durations.top_domains);
+    carousel.add(screens::Top::new(TopList::Clients), durations.top_clients);
+    carousel.add(screens::Tail::default(), durations.tail);
+    carousel
+}
 
-        {
-            let display = &mut d1
-                .lock()
-                .map_err(|_| panic!("Could not lock access to display."))
-                .unwrap();
-            display.clear().unwrap();
-            display
-                .message(&format!(
-                    "Blocked {} ads\n{:.1}% less junk",
-                    status.ads_blocked_today, status.ads_percentage_today,
-                )).unwrap();
-        }
-
-        std::thread::sleep(std::time::Duration::from_secs(10));
+/// Turn blocking off or on from another thread, on each of the instances,
+/// reporting to the main loop, which shows why it failed over the screens.
+fn change_blocking(
+    piholes: Vec<PiHole>,
+    disable_for: Option<Duration>,
+    geometry: Geometry,
+    tx: Sender<Event>,
+) {
+    thread::spawn(move || {
+        let mut errors = vec![];
+        for pihole in &piholes {
+            if let Err(e) = pihole.set_blocking(disable_for) {
+                println!("Could not change blocking on {}: {}", pihole.name(), e);
+                errors.push(match piholes.len() {
+                    1 => e.to_string(),
+                    _ => format!("{}: {}", pihole.name(), e),
+                });
+            }
+        }
+        if errors.len() < piholes.len() {
+            let _ = tx.send(Event::Blocking(disable_for));
+        }
+        if !errors.is_empty() {
+            let mut frame = Frame::new(geometry.cols(), geometry.lines());
+            let text = format!("Blocking unchanged: {}", errors.join(", "));
+            frame.write_lines(0, 0, &layout::wrap(&text, geometry.cols()));
+            let _ = tx.send(Event::Overlay(frame, OVERLAY_DURATION));
+        }
     });
+}
 
-    thread::spawn(move || loop {
+/// End the sessions opened with the instances, so that restarts don't leave
+/// them behind.
+fn logout(piholes: &[PiHole]) {
+    for pihole in piholes {
+        if let Err(e) = pihole.logout() {
+            println!("Could not log out of {}: {}", pihole.name(), e);
+        }
+    }
+}
+
+/// The main loop, the only owner of the display: the other threads send it
+/// events through `tx`, and it reads the buttons itself when they may have changed.
+fn run<B: I2cBus>(
+    mut display: AdafruitDisplay<B>,
+    interrupt_pin: Option<u8>,
+    config: &Config,
+    tx: Sender<Event>,
+    rx: Receiver<Event>,
+) -> Result<(), PiHoleError> {
+    let ctrlc_tx = tx.clone();
+    ctrlc::set_handler(move || {
+        let _ = ctrlc_tx.send(Event::Shutdown);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    // Each instance is polled on its own, so that one that is slow to
+    // answer doesn't hold the others back.
+    let piholes: Vec<PiHole> = config
+        .instances
+        .iter()
+        .map(|instance| PiHole::new(instance, config.timeout))
+        .collect();
+    for (index, pihole) in piholes.iter().enumerate() {
+        let (stats_pihole, stats_tx, interval) = (pihole.clone(), tx.clone(), config.poll_interval);
+        thread::spawn(move || poll_pihole(index, stats_pihole, interval, stats_tx));
+        let (tail_pihole, tail_tx) = (pihole.clone(), tx.clone());
+        thread::spawn(move || tail_queries(tail_pihole, tail_tx));
+    }
+    // Blocking is changed on the instances it can be.
+    let blocking: Vec<PiHole> = piholes
+        .iter()
+        .filter(|pihole| pihole.can_change_blocking())
+        .cloned()
+        .collect();
+    // Without an interrupt line, the buttons are polled.
+    let interrupts = match interrupt_pin {
+        Some(pin) => {
+            display.enable_button_interrupts()?;
+            let line = InterruptLine::new(pin)?;
+            let (read_tx, read_rx) = mpsc::channel();
+            let interrupt_tx = tx.clone();
+            thread::spawn(move || watch_interrupts(line, interrupt_tx, read_rx));
+            Some(read_tx)
+        }
+        None => None,
+    };
+    let mut buttons = ButtonEvents::new(ButtonConfig::default());
+    for button in [Button::Up, Button::Down] {
+        let config = ButtonConfig {
+            repeat: Some(REPEAT),
+            ..ButtonConfig::default()
+        };
+        buttons.configure(button, config);
+    }
+    let mut buttons_read_at = Instant::now();
+    // Button events are handled in turn with the others.
+    let mut pending = VecDeque::new();
+
+    let geometry = display.geometry();
+    let disabled_until = Rc::new(Cell::new(None));
+    let lines = geometry.lines();
+    let mut carousel = carousel(lines, &config.durations, disabled_until.clone(), piholes.len());
+    let mut menu = settings_menu(!blocking.is_empty());
+    // When a button was last pressed in the menu, while it's open.
+    let mut menu_used_at: Option<Instant> = None;
+    let mut backlight = true;
+    let mut color_shown = None;
+    let names = config.instances.iter().map(|i| i.name.clone()).collect();
+    let mut instances = Instances::new(names, config.offline_after, Instant::now());
+    let mut unreachable = screens::Unreachable::default();
+    // When the unreachable screen was first shown, while it is.
+    let mut unreachable_since: Option<Instant> = None;
+    // A frame shown over the screens, and until when.
+    let mut overlay: Option<(Frame, Instant)> = None;
+    loop {
+        let deadline = match (menu_used_at, unreachable_since) {
+            (Some(at), _) => Some(at + MENU_TIMEOUT),
+            (None, Some(_)) => Screen::<B>::refresh_interval(&unreachable)
+                .map(|interval| Instant::now() + interval),
+            (None, None) => carousel.next_deadline(),
+        };
+        // Blocking is back on by itself once the duration is over, and the
+        // age of stale statistics is kept up to date.
+        let stale = Some(Instant::now() + Duration::from_secs(1)).filter(|_| instances.is_stale());
+        let deadline = deadline.into_iter().chain(disabled_until.get()).chain(stale);
+        let poll = Some(buttons_read_at + BUTTON_POLL_INTERVAL).filter(|_| interrupts.is_none());
+        let deadline = deadline
+            .chain(poll)
+            .chain(buttons.next_deadline())
+            .chain(overlay.as_ref().map(|(_, until)| *until))
+            .chain(carousel.backlight(Instant::now()).map(|(_, until)| until))
+            .min();
+        let timeout = deadline.map_or(config.poll_interval, |at| {
+            at.saturating_duration_since(Instant::now())
+        });
+        let received = match pending.pop_front() {
+            Some(event) => Ok(event),
+            None => rx.recv_timeout(timeout),
+        };
+        let now = Instant::now();
+        let mut interrupted = false;
+        match received {
+            Ok(Event::Stats(index, stats)) => {
+                instances.fetched(index, *stats);
+                if let Some(stats) = instances.combined() {
+                    carousel.update(&stats, now);
+                }
+            }
+            Ok(Event::Failed(index, e)) => {
+                unreachable.set_error(&e);
+                instances.failed(index, e);
+                // The page of the instance shows it is down.
+                if let Some(stats) = instances.combined() {
+                    carousel.update(&stats, now);
+                }
+            }
+            Ok(Event::Queries(queries)) => carousel.add_queries(&queries, now),
+            Ok(Event::Interrupt) => interrupted = true,
+            Ok(Event::Button(event)) if menu_used_at.is_some() => {
+                menu_used_at = Some(now);
+                match menu.on_button(event) {
+                    Some(MenuEvent::Toggled(Setting::Backlight, on)) => backlight = on,
+                    Some(MenuEvent::Toggled(Setting::Rotate, on)) => carousel.set_rotating(on),
+                    Some(MenuEvent::Changed(Setting::PageTime, percent)) => {
+                        carousel.set_pace(percent as u32)
+                    }
+                    Some(MenuEvent::Action(Setting::Disable(secs))) => {
+                        let duration = Some(Duration::from_secs(secs));
+                        change_blocking(blocking.clone(), duration, geometry, tx.clone());
+                        menu_used_at = None;
+                        carousel.restart(now);
+                    }
+                    Some(MenuEvent::Action(Setting::Enable)) => {
+                        change_blocking(blocking.clone(), None, geometry, tx.clone());
+                        menu_used_at = None;
+                        carousel.restart(now);
+                    }
+                    Some(MenuEvent::Action(Setting::Quit)) => {
+                        logout(&piholes);
+                        display.set_color(0, 0, 0)?;
+                        return Ok(());
+                    }
+                    Some(MenuEvent::Closed) => {
+                        menu_used_at = None;
+                        carousel.restart(now);
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Button(event)) => {
+                // Select opens the settings, unless a screen uses it.
+                if !carousel.on_button(event, now)
+                    && event == ButtonEvent::Pressed(Button::Select)
+                {
+                    menu.reset();
+                    menu_used_at = Some(now);
+                }
+            }
+            Ok(Event::Blocking(disable_for)) => {
+                disabled_until.set(disable_for.map(|duration| now + duration));
+                // The countdown shows up right away.
+                carousel.show(0, now);
+            }
+            Ok(Event::Overlay(frame, duration)) => overlay = Some((frame, now + duration)),
+            Ok(Event::Shutdown) => {
+                logout(&piholes);
+                display.set_color(0, 0, 0)?;
+                return Ok(());
+            }
+            Ok(Event::Stopped(e)) => return Err(e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let now = Instant::now();
+        let expired = |at: Instant| now >= at;
+        if interrupted
+            || poll.is_some_and(expired)
+            || buttons.next_deadline().is_some_and(expired)
         {
-            let display = &mut display
-                .lock()
-                .map_err(|_| panic!("Could not lock access to display."))
-                .unwrap();
-            if display.is_pressed(Button::Select).unwrap() {
-                display.toggle_backlight().unwrap();
+            let mut readings = vec![];
+            if let Some(ref read) = interrupts {
+                // Catch presses that were over by the time the interrupt got serviced.
+                readings.extend(display.button_interrupt()?);
+                let _ = read.send(());
+            }
+            readings.push(display.pressed_buttons()?);
+            buttons_read_at = now;
+            for pressed in readings {
+                pending.extend(buttons.update(pressed, now).into_iter().map(Event::Button));
+            }
+        }
+
+        if disabled_until.get().is_some_and(|until| now >= until) {
+            disabled_until.set(None);
+        }
+        // The flashes of the screens don't show over the menu and overlays.
+        let flash = carousel
+            .backlight(now)
+            .filter(|_| menu_used_at.is_none() && overlay.is_none());
+        // Blocking may also have been turned off on an instance from its web interface.
+        let disabled = disabled_until.get().is_some() || instances.is_disabled();
+        let color = match (backlight, instances.is_unreachable(now), flash, disabled) {
+            (false, _, _, _) => (0, 0, 0),
+            (true, true, _, _) => UNREACHABLE_COLOR,
+            (true, false, Some((color, _)), _) => color,
+            (true, false, None, true) => DISABLED_COLOR,
+            (true, false, None, false) => (255, 255, 255),
+        };
+        if color_shown != Some(color) {
+            display.set_color(color.0, color.1, color.2)?;
+            color_shown = Some(color);
+        }
+
+        carousel.set_marker(instances.marker(now));
+        unreachable_since = match (instances.is_unreachable(now), unreachable_since) {
+            (true, since) => Some(since.unwrap_or(now)),
+            (false, Some(_)) => {
+                carousel.restart(now);
+                None
             }
+            (false, None) => None,
+        };
+        if overlay.as_ref().is_some_and(|(_, until)| now >= *until) {
+            overlay = None;
+            carousel.restart(now);
         }
+        match menu_used_at {
+            Some(at) if now < at + MENU_TIMEOUT => {
+                let mut frame = display.frame();
+                menu.draw(&mut frame);
+                display.render(&frame)?;
+            }
+            _ => {
+                if menu_used_at.take().is_some() {
+                    carousel.restart(now);
+                }
+                match (&overlay, unreachable_since) {
+                    (Some((frame, _)), _) => display.render(frame)?,
+                    (None, Some(since)) => unreachable.render(&mut display, now - since)?,
+                    (None, None) => carousel.tick(&mut display, now)?,
+                }
+            }
+        }
+    }
+}
 
-        std::thread::sleep(std::time::Duration::from_millis(200));
-    }).join()
-    .unwrap();
+/// What can be changed from the menu.
+#[derive(Clone, Debug, PartialEq)]
+enum Setting {
+    Backlight,
+    Rotate,
+    /// How long the screens are shown, in percents of their durations.
+    PageTime,
+    /// Turn blocking off for the given number of seconds.
+    Disable(u64),
+    Enable,
+    Quit,
+}
+
+/// Blocking can only be changed with the API token.
+fn settings_menu(blocking: bool) -> Menu<Setting> {
+    let mut items = vec![];
+    if blocking {
+        items.push(Item::submenu(
+            "Blocking",
+            vec![
+                Item::action("Off for 30s", Setting::Disable(30)),
+                Item::action("Off for 5min", Setting::Disable(5 * 60)),
+                Item::action("Off for 1h", Setting::Disable(60 * 60)),
+                Item::action("Back on", Setting::Enable),
+            ],
+        ));
+    }
+    items.extend(vec![
+        Item::submenu(
+            "Display",
+            vec![
+                Item::checkbox("Backlight", Setting::Backlight, true),
+                Item::checkbox("Rotate pages", Setting::Rotate, true),
+                // A share of the durations configured for each screen.
+                Item::stepper("Page time", Setting::PageTime, 100, 25..=400)
+                    .step(25)
+                    .unit("%"),
+            ],
+        ),
+        Item::confirm("Quit", Setting::Quit),
+    ]);
+    Menu::new(items)
+}
 
-    Ok(())
+/// What the main loop reacts to.
+enum Event {
+    /// The statistics of the instance at the given index were fetched.
+    Stats(usize, Box<Stats>),
+    /// The statistics of the instance at the given index couldn't be fetched.
+    Failed(usize, PiHoleError),
+    /// Queries were answered since the last ones, oldest first.
+    Queries(Vec<PiHoleQuery>),
+    /// The plate raised an interrupt, the buttons changed.
+    Interrupt,
+    Button(ButtonEvent),
+    /// Blocking was turned off for the given duration, or back on.
+    Blocking(Option<Duration>),
+    /// Show a frame over the screens for a while.
+    Overlay(Frame, Duration),
+    /// Turn the backlight off and quit, on Ctrl-C.
+    Shutdown,
+    /// Watching the interrupt line failed.
+    Stopped(PiHoleError),
 }
 
-#[derive(Deserialize, Debug)]
-struct PiHoleStatus {
-    domains_being_blocked: usize,
-    dns_queries_today: usize,
-    ads_blocked_today: usize,
-    ads_percentage_today: f32,
-    unique_domains: usize,
-    queries_forwarded: usize,
-    queries_cached: usize,
-    dns_queries_all_types: usize,
+/// Everything fetched from an instance at once, or from all of them added up.
+pub struct Stats {
+    status: PiHoleStatus,
+    /// Missing when it couldn't be fetched.
+    history: Option<PiHoleHistory>,
+    /// Missing when it couldn't be fetched, like the history.
+    top: Option<PiHoleTop>,
+    /// Missing when it couldn't be fetched, like the history.
+    shares: Option<PiHoleShares>,
+    /// How each instance is doing, once their statistics are combined.
+    instances: Vec<InstanceStatus>,
+    fetched_at: Instant,
+}
+
+/// Nothing fetched but the counters, all zero, for the tests to fill in.
+#[cfg(test)]
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            status: PiHoleStatus::default(),
+            history: None,
+            top: None,
+            shares: None,
+            instances: vec![],
+            fetched_at: Instant::now(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -137,6 +608,24 @@ pub enum PiHoleError {
     HttpError(std::io::Error),
     DataError(serde_json::Error),
     DeviceError(CommunicationError),
+    TerminalError(std::io::Error),
+    /// The API token or the password is missing or wrong.
+    Unauthorized,
+    /// An option or the config file is invalid.
+    ConfigError(String),
+}
+
+impl fmt::Display for PiHoleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PiHoleError::HttpError(e) => write!(f, "Could not reach Pi-hole: {}", e),
+            PiHoleError::DataError(e) => write!(f, "Unexpected answer of Pi-hole: {}", e),
+            PiHoleError::DeviceError(e) => write!(f, "LCD plate error: {}", e),
+            PiHoleError::TerminalError(e) => write!(f, "Terminal error: {}", e),
+            PiHoleError::Unauthorized => write!(f, "Wrong API token or password"),
+            PiHoleError::ConfigError(message) => write!(f, "{}", message),
+        }
+    }
 }
 
 impl From<serde_json::Error> for PiHoleError {
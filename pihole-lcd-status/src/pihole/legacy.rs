@@ -0,0 +1,315 @@
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{
+    bad_status, PiHole, PiHoleHistory, PiHoleQuery, PiHoleShares, PiHoleStatus, PiHoleTop,
+};
+use crate::PiHoleError;
+
+const API: &str = "/admin/api.php";
+
+/// What `api.php` answers without parameters, the same as `?summaryRaw`.
+#[derive(Deserialize, Debug)]
+struct Summary {
+    domains_being_blocked: usize,
+    dns_queries_today: usize,
+    ads_blocked_today: usize,
+    ads_percentage_today: f32,
+    unique_domains: usize,
+    queries_forwarded: usize,
+    queries_cached: usize,
+    /// `enabled` or `disabled`.
+    #[serde(default)]
+    status: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OverTime {
+    domains_over_time: BTreeMap<u64, u64>,
+    ads_over_time: BTreeMap<u64, u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopItems {
+    top_queries: BTreeMap<String, u64>,
+    top_ads: BTreeMap<String, u64>,
+}
+
+/// Clients are named `hostname|address`, the host name being empty when unknown.
+#[derive(Deserialize, Debug)]
+struct QuerySources {
+    top_sources: BTreeMap<String, u64>,
+}
+
+/// Percentages of all the queries by destination, named `name|address`,
+/// the blocked and cached queries being listed as destinations as well.
+#[derive(Deserialize, Debug)]
+struct ForwardDestinations {
+    forward_destinations: BTreeMap<String, f32>,
+}
+
+/// Percentages by type, named like `A (IPv4)`.
+#[derive(Deserialize, Debug)]
+struct QueryTypes {
+    querytypes: BTreeMap<String, f32>,
+}
+
+/// Each query is a row of the timestamp, type, domain, client and status,
+/// followed by other fields, mostly as strings.
+#[derive(Deserialize, Debug)]
+struct AllQueries {
+    data: Vec<Vec<serde_json::Value>>,
+}
+
+/// Statuses of the blocked queries: by gravity, a regex, the blacklist,
+/// upstream, or for a CNAME.
+const BLOCKED: [u64; 11] = [1, 4, 5, 6, 7, 8, 9, 10, 11, 15, 16];
+
+fn get<T: DeserializeOwned>(pihole: &PiHole, query: &str) -> Result<T, PiHoleError> {
+    let path = match query {
+        "" => API.to_string(),
+        query => format!("{}?{}", API, query),
+    };
+    match pihole.send("GET", &path, &[], None)? {
+        (200, body) => Ok(serde_json::from_str(&body)?),
+        (status, _) => Err(bad_status(status, API)),
+    }
+}
+
+/// Whether `api.php` answers with the summary, telling the older versions apart.
+pub fn answers(pihole: &PiHole) -> Result<bool, PiHoleError> {
+    match pihole.send("GET", API, &[], None)? {
+        (200, body) => Ok(serde_json::from_str::<Summary>(&body).is_ok()),
+        _ => Ok(false),
+    }
+}
+
+pub fn status(pihole: &PiHole) -> Result<PiHoleStatus, PiHoleError> {
+    let summary: Summary = get(pihole, "")?;
+    Ok(PiHoleStatus {
+        domains_being_blocked: summary.domains_being_blocked,
+        dns_queries_today: summary.dns_queries_today,
+        ads_blocked_today: summary.ads_blocked_today,
+        ads_percentage_today: summary.ads_percentage_today,
+        unique_domains: summary.unique_domains,
+        queries_forwarded: summary.queries_forwarded,
+        queries_cached: summary.queries_cached,
+        blocking: summary.status.as_deref() != Some("disabled"),
+    })
+}
+
+pub fn history(pihole: &PiHole) -> Result<PiHoleHistory, PiHoleError> {
+    let over_time: OverTime = get(pihole, "overTimeData10mins")?;
+    Ok(PiHoleHistory {
+        domains_over_time: over_time.domains_over_time,
+        ads_over_time: over_time.ads_over_time,
+    })
+}
+
+/// Query needing the API token, which Pi-hole answers with an empty list
+/// when the token is wrong.
+fn get_authenticated<T: DeserializeOwned>(pihole: &PiHole, query: &str) -> Result<T, PiHoleError> {
+    let token = pihole.token.as_ref().ok_or(PiHoleError::Unauthorized)?;
+    let response: serde_json::Value = get(pihole, &format!("{}&auth={}", query, token))?;
+    if response.is_array() {
+        return Err(PiHoleError::Unauthorized);
+    }
+    Ok(serde_json::from_value(response)?)
+}
+
+pub fn top(pihole: &PiHole, count: usize) -> Result<PiHoleTop, PiHoleError> {
+    let items: TopItems = get_authenticated(pihole, &format!("topItems={}", count))?;
+    let sources: QuerySources = get_authenticated(pihole, &format!("getQuerySources={}", count))?;
+    let clients = sources.top_sources.into_iter().map(|(client, queries)| {
+        let name = match client.split_once('|') {
+            Some(("", address)) => address.to_string(),
+            Some((host, _)) => host.to_string(),
+            None => client,
+        };
+        (name, queries)
+    });
+    Ok(PiHoleTop {
+        blocked: PiHoleTop::ranked(items.top_ads, count),
+        permitted: PiHoleTop::ranked(items.top_queries, count),
+        clients: PiHoleTop::ranked(clients, count),
+    })
+}
+
+pub fn shares(pihole: &PiHole) -> Result<PiHoleShares, PiHoleError> {
+    let destinations: ForwardDestinations = get_authenticated(pihole, "getForwardDestinations")?;
+    let upstreams = destinations
+        .forward_destinations
+        .into_iter()
+        .filter(|(destination, _)| !destination.starts_with("blocklist|"))
+        .filter(|(destination, _)| !destination.starts_with("cache|"))
+        .map(|(destination, share)| {
+            let name = match destination.split_once('|') {
+                Some(("", address)) => address,
+                Some((name, _)) => name,
+                None => &destination[..],
+            };
+            // Resolvers may be listed along with their port, e.g. `dns.google#53`.
+            let name = name.split('#').next().unwrap_or(name);
+            (name.to_string(), share)
+        });
+    let types: QueryTypes = get_authenticated(pihole, "getQueryTypes")?;
+    let query_types = types.querytypes.into_iter().map(|(name, share)| {
+        let name = name.split(" (").next().unwrap_or(&name).to_string();
+        (name, share)
+    });
+    Ok(PiHoleShares {
+        upstreams: PiHoleShares::normalized(upstreams),
+        query_types: PiHoleShares::normalized(query_types),
+    })
+}
+
+pub fn queries(pihole: &PiHole, count: usize) -> Result<Vec<(u64, PiHoleQuery)>, PiHoleError> {
+    let all: AllQueries = get_authenticated(pihole, &format!("getAllQueries={}", count))?;
+    let field = |row: &[serde_json::Value], i: usize| match row.get(i) {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+    let mut queries: Vec<_> = all
+        .data
+        .iter()
+        .filter_map(|row| {
+            let timestamp = field(row, 0).parse().ok()?;
+            let status = field(row, 4).parse().unwrap_or(0);
+            let query = PiHoleQuery {
+                domain: field(row, 2),
+                client: field(row, 3),
+                blocked: BLOCKED.contains(&status),
+            };
+            Some((timestamp, query))
+        })
+        .collect();
+    queries.sort_by_key(|(timestamp, _)| *timestamp);
+    Ok(queries)
+}
+
+/// Authenticated with the API token shown in the settings of the web interface.
+pub fn set_blocking(pihole: &PiHole, disable_for: Option<Duration>) -> Result<(), PiHoleError> {
+    let token = pihole.token.as_ref().ok_or(PiHoleError::Unauthorized)?;
+    let query = match disable_for {
+        Some(duration) => format!("disable={}&auth={}", duration.as_secs(), token),
+        None => format!("enable&auth={}", token),
+    };
+    // Pi-hole answers with an empty list when the token is wrong.
+    let response: serde_json::Value = get(pihole, &query)?;
+    match response.get("status") {
+        Some(_) => Ok(()),
+        None => Err(PiHoleError::Unauthorized),
+    }
+}
+
+#[test]
+fn legacy_api() {
+    use super::standin::StandIn;
+    use super::Generation;
+
+    let server = StandIn::start(|request| match request.path.as_str() {
+        "/admin/api.php" => (
+            200,
+            r#"{"domains_being_blocked":120000,"dns_queries_today":5000,
+                "ads_blocked_today":500,"ads_percentage_today":10.0,"unique_domains":800,
+                "queries_forwarded":3000,"queries_cached":1500,"status":"enabled"}"#
+                .to_string(),
+        ),
+        "/admin/api.php?overTimeData10mins" => (
+            200,
+            r#"{"domains_over_time":{"1600000000":10,"1600000600":20},
+                "ads_over_time":{"1600000000":1,"1600000600":2}}"#
+                .to_string(),
+        ),
+        "/admin/api.php?disable=30&auth=secret" => (200, r#"{"status":"disabled"}"#.to_string()),
+        "/admin/api.php?topItems=2&auth=secret" => (
+            200,
+            r#"{"top_queries":{"a.example":30,"b.example":50},
+                "top_ads":{"ads.example":20,"track.example":20}}"#
+                .to_string(),
+        ),
+        "/admin/api.php?getQuerySources=2&auth=secret" => (
+            200,
+            r#"{"top_sources":{"laptop.lan|192.168.1.10":90,"|192.168.1.11":40}}"#.to_string(),
+        ),
+        path if path.starts_with("/admin/api.php?topItems") => (200, "[]".to_string()),
+        "/admin/api.php?getForwardDestinations&auth=secret" => (
+            200,
+            r#"{"forward_destinations":{"blocklist|blocklist":10.0,"cache|cache":30.0,
+                "dns.google#53|8.8.8.8#53":45.0,"|1.1.1.1#53":15.0}}"#
+                .to_string(),
+        ),
+        "/admin/api.php?getQueryTypes&auth=secret" => (
+            200,
+            r#"{"querytypes":{"A (IPv4)":75.0,"AAAA (IPv6)":25.0,"ANY":0.0}}"#.to_string(),
+        ),
+        "/admin/api.php?getAllQueries=3&auth=secret" => (
+            200,
+            r#"{"data":[["1600000100","A","ads.example","laptop.lan","1","0"],
+                ["1600000101","AAAA","example.com","192.168.1.11","2","0"]]}"#
+                .to_string(),
+        ),
+        path if path.starts_with("/admin/api.php?disable") => (200, "[]".to_string()),
+        _ => (404, "Not Found".to_string()),
+    });
+
+    let pihole = server.client(Some("secret"));
+    assert_eq!(pihole.generation().unwrap(), Generation::Legacy);
+    let status = pihole.status().unwrap();
+    assert_eq!(status.dns_queries_today, 5000);
+    assert_eq!(status.queries_cached, 1500);
+    assert!(status.blocking);
+    let history = pihole.history().unwrap();
+    assert_eq!(history.ads_over_time.get(&1600000600), Some(&2));
+
+    let top = pihole.top(2).unwrap();
+    assert_eq!(top.permitted[0], ("b.example".to_string(), 50));
+    assert_eq!(top.blocked[1].0, "track.example");
+    assert_eq!(
+        top.clients,
+        vec![
+            ("laptop.lan".to_string(), 90),
+            ("192.168.1.11".to_string(), 40)
+        ]
+    );
+
+    let shares = pihole.shares().unwrap();
+    assert_eq!(
+        shares.upstreams,
+        vec![
+            ("dns.google".to_string(), 0.75),
+            ("1.1.1.1".to_string(), 0.25)
+        ]
+    );
+    assert_eq!(
+        shares.query_types,
+        vec![("A".to_string(), 0.75), ("AAAA".to_string(), 0.25)]
+    );
+
+    let mut cursor = Default::default();
+    let queries = pihole.queries(&mut cursor, 3).unwrap();
+    assert_eq!(queries.len(), 2);
+    assert!(queries[0].blocked && !queries[1].blocked);
+    assert_eq!(queries[1].client, "192.168.1.11");
+    assert_eq!(pihole.queries(&mut cursor, 3).unwrap(), vec![]);
+
+    pihole.set_blocking(Some(Duration::from_secs(30))).unwrap();
+    match server
+        .client(Some("wrong"))
+        .set_blocking(Some(Duration::from_secs(30)))
+    {
+        Err(PiHoleError::Unauthorized) => {}
+        other => panic!("{:?}", other),
+    }
+    match server.client(Some("wrong")).top(2) {
+        Err(PiHoleError::Unauthorized) => {}
+        other => panic!("{:?}", other),
+    }
+    // Detection is only done once.
+    let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths.iter().filter(|path| *path == "/api/auth").count(), 3);
+}
@@ -0,0 +1,513 @@
+//! Client of Pi-hole, talking to the REST API of v6 or to `admin/api.php`
+//! of the older versions, whichever is running.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::config::Instance;
+use crate::PiHoleError;
+
+mod legacy;
+#[cfg(test)]
+mod standin;
+mod v6;
+
+/// Counters of the last 24 hours, whatever the API they came from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PiHoleStatus {
+    pub domains_being_blocked: usize,
+    pub dns_queries_today: usize,
+    pub ads_blocked_today: usize,
+    pub ads_percentage_today: f32,
+    pub unique_domains: usize,
+    pub queries_forwarded: usize,
+    pub queries_cached: usize,
+    /// Whether blocking is on.
+    pub blocking: bool,
+}
+
+impl PiHoleStatus {
+    /// The counters of several instances added up. They mostly share their
+    /// blocklists and the domains they're asked for, so the largest counts
+    /// are kept for those. Blocking is on only when it is on each instance.
+    pub fn combined<'a>(statuses: impl IntoIterator<Item = &'a PiHoleStatus>) -> PiHoleStatus {
+        let mut combined = PiHoleStatus {
+            blocking: true,
+            ..PiHoleStatus::default()
+        };
+        for status in statuses {
+            combined.domains_being_blocked = combined
+                .domains_being_blocked
+                .max(status.domains_being_blocked);
+            combined.dns_queries_today += status.dns_queries_today;
+            combined.ads_blocked_today += status.ads_blocked_today;
+            combined.unique_domains = combined.unique_domains.max(status.unique_domains);
+            combined.queries_forwarded += status.queries_forwarded;
+            combined.queries_cached += status.queries_cached;
+            combined.blocking &= status.blocking;
+        }
+        if combined.dns_queries_today > 0 {
+            combined.ads_percentage_today =
+                combined.ads_blocked_today as f32 * 100.0 / combined.dns_queries_today as f32;
+        }
+        combined
+    }
+}
+
+/// Number of queries per 10 minutes slot over the last 24 hours, by timestamp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PiHoleHistory {
+    pub domains_over_time: BTreeMap<u64, u64>,
+    pub ads_over_time: BTreeMap<u64, u64>,
+}
+
+impl PiHoleHistory {
+    /// The queries of several instances added up, slot by slot.
+    pub fn combined<'a>(histories: impl IntoIterator<Item = &'a PiHoleHistory>) -> PiHoleHistory {
+        let mut combined = PiHoleHistory {
+            domains_over_time: BTreeMap::new(),
+            ads_over_time: BTreeMap::new(),
+        };
+        for history in histories {
+            for (timestamp, count) in &history.domains_over_time {
+                *combined.domains_over_time.entry(*timestamp).or_insert(0) += count;
+            }
+            for (timestamp, count) in &history.ads_over_time {
+                *combined.ads_over_time.entry(*timestamp).or_insert(0) += count;
+            }
+        }
+        combined
+    }
+}
+
+/// The most queried domains and the most active clients of the last 24
+/// hours, with their number of queries, the most frequent first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PiHoleTop {
+    pub blocked: Vec<(String, u64)>,
+    pub permitted: Vec<(String, u64)>,
+    /// By host name when it is known, otherwise by address.
+    pub clients: Vec<(String, u64)>,
+}
+
+impl PiHoleTop {
+    /// Order entries the most frequent first, keeping the first `count`.
+    fn ranked(
+        entries: impl IntoIterator<Item = (String, u64)>,
+        count: usize,
+    ) -> Vec<(String, u64)> {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        entries.truncate(count);
+        entries
+    }
+
+    /// The lists of several instances merged, adding up the queries of the
+    /// entries they share, as long as the longest of them.
+    pub fn combined<'a>(tops: impl IntoIterator<Item = &'a PiHoleTop>) -> PiHoleTop {
+        let mut blocked = BTreeMap::new();
+        let mut permitted = BTreeMap::new();
+        let mut clients = BTreeMap::new();
+        let mut count = 0;
+        for top in tops {
+            let lists = [
+                (&mut blocked, &top.blocked),
+                (&mut permitted, &top.permitted),
+                (&mut clients, &top.clients),
+            ];
+            for (merged, list) in lists {
+                count = count.max(list.len());
+                for (name, queries) in list {
+                    *merged.entry(name.clone()).or_insert(0) += queries;
+                }
+            }
+        }
+        PiHoleTop {
+            blocked: PiHoleTop::ranked(blocked, count),
+            permitted: PiHoleTop::ranked(permitted, count),
+            clients: PiHoleTop::ranked(clients, count),
+        }
+    }
+}
+
+/// How the queries of the last 24 hours split, as shares between 0 and 1,
+/// the largest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PiHoleShares {
+    /// By upstream resolver, among the forwarded queries.
+    pub upstreams: Vec<(String, f32)>,
+    /// By type of record, e.g. A or AAAA.
+    pub query_types: Vec<(String, f32)>,
+}
+
+impl PiHoleShares {
+    /// Turn counts, or percentages, into shares of their total, leaving
+    /// out the empty ones.
+    fn normalized(entries: impl IntoIterator<Item = (String, f32)>) -> Vec<(String, f32)> {
+        let mut entries: Vec<_> = entries.into_iter().filter(|(_, n)| *n > 0.0).collect();
+        let total: f32 = entries.iter().map(|(_, n)| n).sum();
+        for entry in entries.iter_mut() {
+            entry.1 /= total;
+        }
+        entries.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        entries
+    }
+
+    /// The splits of several instances merged, each weighted by the number
+    /// of queries it was made from.
+    pub fn combined<'a>(
+        parts: impl IntoIterator<Item = (&'a PiHoleShares, &'a PiHoleStatus)>,
+    ) -> PiHoleShares {
+        let mut upstreams = BTreeMap::new();
+        let mut query_types = BTreeMap::new();
+        for (shares, status) in parts {
+            for (name, share) in &shares.upstreams {
+                *upstreams.entry(name.clone()).or_insert(0.0) +=
+                    share * status.queries_forwarded as f32;
+            }
+            for (name, share) in &shares.query_types {
+                *query_types.entry(name.clone()).or_insert(0.0) +=
+                    share * status.dns_queries_today as f32;
+            }
+        }
+        PiHoleShares {
+            upstreams: PiHoleShares::normalized(upstreams),
+            query_types: PiHoleShares::normalized(query_types),
+        }
+    }
+}
+
+/// A DNS query from the query log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PiHoleQuery {
+    pub domain: String,
+    /// By host name when it is known, otherwise by address.
+    pub client: String,
+    pub blocked: bool,
+}
+
+/// How far the query log was read, so that each query is only returned once.
+#[derive(Clone, Debug, Default)]
+pub struct QueryCursor {
+    /// The timestamp, or ID, of the last query returned, and the number of
+    /// queries returned with it, several queries sharing a timestamp.
+    last: Option<(u64, usize)>,
+}
+
+impl QueryCursor {
+    /// The timestamp, or ID, of the last query returned.
+    fn last(&self) -> Option<u64> {
+        self.last.map(|(key, _)| key)
+    }
+
+    /// Keep the queries past the cursor, given oldest first along with
+    /// their timestamp or ID, and move the cursor after them.
+    fn advance(&mut self, queries: Vec<(u64, PiHoleQuery)>) -> Vec<PiHoleQuery> {
+        let (last, seen) = self.last.unwrap_or((0, 0));
+        let mut skipped = 0;
+        let mut new = vec![];
+        for (key, query) in queries {
+            if key < last || (key == last && skipped < seen) {
+                skipped += usize::from(key == last);
+                continue;
+            }
+            new.push((key, query));
+        }
+        if let Some(&(key, _)) = new.last() {
+            let count = new.iter().filter(|(k, _)| *k == key).count();
+            self.last = Some((key, count + if key == last { seen } else { 0 }));
+        }
+        new.into_iter().map(|(_, query)| query).collect()
+    }
+}
+
+/// The API spoken by Pi-hole.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generation {
+    /// `admin/api.php`, until Pi-hole v5.
+    Legacy,
+    /// The REST API under `/api`, with session authentication.
+    V6,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    generation: Option<Generation>,
+    session: Option<v6::Session>,
+}
+
+/// Clones share the API detected and the session, so that a single session
+/// is opened whatever the number of threads using the client.
+#[derive(Clone, Debug)]
+pub struct PiHole {
+    /// The name of the instance, in messages.
+    name: String,
+    /// URL of the web server of Pi-hole, without a trailing slash.
+    base: String,
+    /// API token until v5, password or app password from v6.
+    token: Option<String>,
+    timeout: Duration,
+    state: Arc<Mutex<State>>,
+    /// Held while logging in, so that the threads finding the session
+    /// missing or expiring at the same time only open one new session.
+    login: Arc<Mutex<()>>,
+}
+
+impl PiHole {
+    /// A client of the instance at its endpoint: the URL of its web server,
+    /// or of `admin/api.php` to only use the legacy API.
+    pub fn new(instance: &Instance, timeout: Duration) -> Self {
+        let endpoint = instance.endpoint.trim_end_matches('/');
+        let (base, generation) = match endpoint.strip_suffix("/admin/api.php") {
+            Some(base) => (base, Some(Generation::Legacy)),
+            None => (endpoint, None),
+        };
+        PiHole {
+            name: instance.name.clone(),
+            base: base.to_string(),
+            token: instance.token.clone(),
+            timeout,
+            state: Arc::new(Mutex::new(State {
+                generation,
+                session: None,
+            })),
+            login: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether blocking can be turned on and off, which needs a token or a
+    /// password.
+    pub fn can_change_blocking(&self) -> bool {
+        self.token.is_some()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state is consistent whatever thread panicked while holding it.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The API spoken by Pi-hole, detected on first use: v6 answers on
+    /// `/api/auth`, whether a password is set or not, where older versions
+    /// have nothing. Any other answer, e.g. from a proxy while Pi-hole
+    /// restarts, is an error, and detection is tried again on next use.
+    pub fn generation(&self) -> Result<Generation, PiHoleError> {
+        if let Some(generation) = self.state().generation {
+            return Ok(generation);
+        }
+        let generation = match self.send("GET", "/api/auth", &[], None)? {
+            (200, ref body) | (401, ref body) if v6::is_auth_response(body) => Generation::V6,
+            (404, _) => Generation::Legacy,
+            // Some web servers answer unknown paths with a page of their own.
+            (200, _) if legacy::answers(self)? => Generation::Legacy,
+            (status, _) => return Err(bad_status(status, "/api/auth")),
+        };
+        self.state().generation = Some(generation);
+        Ok(generation)
+    }
+
+    pub fn status(&self) -> Result<PiHoleStatus, PiHoleError> {
+        match self.generation()? {
+            Generation::Legacy => legacy::status(self),
+            Generation::V6 => v6::status(self),
+        }
+    }
+
+    pub fn history(&self) -> Result<PiHoleHistory, PiHoleError> {
+        match self.generation()? {
+            Generation::Legacy => legacy::history(self),
+            Generation::V6 => v6::history(self),
+        }
+    }
+
+    /// The first `count` entries of the top lists. Pi-hole v5 only shares
+    /// them with the API token.
+    pub fn top(&self, count: usize) -> Result<PiHoleTop, PiHoleError> {
+        match self.generation()? {
+            Generation::Legacy => legacy::top(self, count),
+            Generation::V6 => v6::top(self, count),
+        }
+    }
+
+    /// How the queries split by upstream resolver and by type. Pi-hole v5
+    /// only shares them with the API token.
+    pub fn shares(&self) -> Result<PiHoleShares, PiHoleError> {
+        match self.generation()? {
+            Generation::Legacy => legacy::shares(self),
+            Generation::V6 => v6::shares(self),
+        }
+    }
+
+    /// The queries answered since the cursor, oldest first, out of the
+    /// last `count` ones. Pi-hole v5 only shares them with the API token.
+    pub fn queries(
+        &self,
+        cursor: &mut QueryCursor,
+        count: usize,
+    ) -> Result<Vec<PiHoleQuery>, PiHoleError> {
+        let queries = match self.generation()? {
+            Generation::Legacy => legacy::queries(self, count)?,
+            Generation::V6 => v6::queries(self, cursor.last(), count)?,
+        };
+        Ok(cursor.advance(queries))
+    }
+
+    /// Turn blocking off for the given duration, or back on.
+    pub fn set_blocking(&self, disable_for: Option<Duration>) -> Result<(), PiHoleError> {
+        match self.generation()? {
+            Generation::Legacy => legacy::set_blocking(self, disable_for),
+            Generation::V6 => v6::set_blocking(self, disable_for),
+        }
+    }
+
+    /// End the session opened with Pi-hole v6, if any, so that it doesn't
+    /// take one of the few session slots of Pi-hole until it expires.
+    pub fn logout(&self) -> Result<(), PiHoleError> {
+        let generation = self.state().generation;
+        match generation {
+            Some(Generation::V6) => v6::logout(self),
+            _ => Ok(()),
+        }
+    }
+
+    /// Send a request to the web server of Pi-hole, returning the status
+    /// code and the body of the response.
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+    ) -> Result<(u16, String), PiHoleError> {
+        let millis = self.timeout.as_millis() as u64;
+        let mut request = ureq::request(method, &format!("{}{}", self.base, path));
+        request.timeout_connect(millis).timeout_read(millis);
+        for (name, value) in headers {
+            request.set(name, value);
+        }
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(body),
+            None => request.call(),
+        };
+        if let Some(ref e) = *response.synthetic_error() {
+            return Err(PiHoleError::HttpError(io::Error::other(e.body_text())));
+        }
+        let status = response.status();
+        Ok((status, response.into_string()?))
+    }
+}
+
+/// An error for unexpected status codes.
+fn bad_status(status: u16, path: &str) -> PiHoleError {
+    PiHoleError::HttpError(io::Error::other(format!("HTTP {} for {}", status, path)))
+}
+
+#[test]
+fn query_cursor() {
+    let query = |domain: &str| PiHoleQuery {
+        domain: domain.to_string(),
+        client: "laptop".to_string(),
+        blocked: false,
+    };
+    let domains = |queries: Vec<PiHoleQuery>| -> Vec<String> {
+        queries.into_iter().map(|query| query.domain).collect()
+    };
+    let mut cursor = QueryCursor::default();
+    let first = vec![(10, query("a")), (11, query("b")), (11, query("c"))];
+    assert_eq!(domains(cursor.advance(first)), vec!["a", "b", "c"]);
+    // Queries of the same second are told apart by their number.
+    let second = vec![
+        (11, query("b")),
+        (11, query("c")),
+        (11, query("d")),
+        (12, query("e")),
+    ];
+    assert_eq!(domains(cursor.advance(second)), vec!["d", "e"]);
+    assert_eq!(cursor.advance(vec![(12, query("e"))]), vec![]);
+}
+
+#[test]
+fn detection() {
+    use standin::StandIn;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let a = attempts.clone();
+    let server = StandIn::start(move |request| match request.path.as_str() {
+        // A proxy answers while Pi-hole restarts.
+        "/api/auth" if a.fetch_add(1, Ordering::SeqCst) == 0 => {
+            (503, "<html>Service Unavailable</html>".to_string())
+        }
+        "/api/auth" => (
+            401,
+            r#"{"session":{"valid":false,"totp":false,"sid":null,"validity":-1}}"#.to_string(),
+        ),
+        _ => (404, "Not Found".to_string()),
+    });
+    let pihole = server.client(None);
+    assert!(pihole.generation().is_err());
+    assert_eq!(pihole.state().generation, None);
+    assert_eq!(pihole.generation().unwrap(), Generation::V6);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn combined() {
+    let status = |queries: usize, blocked: usize, blocking: bool| PiHoleStatus {
+        dns_queries_today: queries,
+        ads_blocked_today: blocked,
+        queries_forwarded: queries / 2,
+        domains_being_blocked: 1000,
+        blocking,
+        ..PiHoleStatus::default()
+    };
+    let primary = status(3000, 600, true);
+    let secondary = status(1000, 0, false);
+    let combined = PiHoleStatus::combined(vec![&primary, &secondary]);
+    assert_eq!(combined.dns_queries_today, 4000);
+    assert_eq!(combined.ads_percentage_today, 15.0);
+    assert_eq!(combined.domains_being_blocked, 1000);
+    assert!(!combined.blocking);
+
+    let top = |clients: Vec<(&str, u64)>| PiHoleTop {
+        clients: clients
+            .into_iter()
+            .map(|(name, n)| (name.to_string(), n))
+            .collect(),
+        ..PiHoleTop::default()
+    };
+    let tops = [
+        top(vec![("laptop", 50), ("tv", 40)]),
+        top(vec![("phone", 60), ("laptop", 20)]),
+    ];
+    let combined = PiHoleTop::combined(&tops);
+    assert_eq!(
+        combined.clients,
+        vec![("laptop".to_string(), 70), ("phone".to_string(), 60)]
+    );
+
+    let shares = |upstreams: Vec<(&str, f32)>| PiHoleShares {
+        upstreams: upstreams
+            .into_iter()
+            .map(|(name, share)| (name.to_string(), share))
+            .collect(),
+        ..PiHoleShares::default()
+    };
+    let first = shares(vec![("dns.google", 0.5), ("1.1.1.1", 0.5)]);
+    let second = shares(vec![("dns.google", 1.0)]);
+    let combined = PiHoleShares::combined(vec![(&first, &primary), (&second, &secondary)]);
+    assert_eq!(
+        combined.upstreams,
+        vec![
+            ("dns.google".to_string(), 0.625),
+            ("1.1.1.1".to_string(), 0.375)
+        ]
+    );
+}
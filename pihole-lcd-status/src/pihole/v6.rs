@@ -0,0 +1,636 @@
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use super::{
+    bad_status, PiHole, PiHoleHistory, PiHoleQuery, PiHoleShares, PiHoleStatus, PiHoleTop,
+};
+use crate::PiHoleError;
+
+/// How many pages of the query log are read at most to catch up with it,
+/// e.g. after Pi-hole was unreachable for a while.
+const MAX_PAGES: usize = 10;
+
+/// How long before it expires a session is renewed, so that it doesn't
+/// expire while a request is on its way.
+const RENEWAL_MARGIN: Duration = Duration::from_secs(10);
+
+/// A session opened with `/api/auth`, which Pi-hole extends each time it's used.
+#[derive(Debug)]
+pub struct Session {
+    /// `None` when Pi-hole has no password.
+    sid: Option<String>,
+    validity: Duration,
+    expires_at: Instant,
+}
+
+#[derive(Deserialize, Debug)]
+struct Auth {
+    session: SessionInfo,
+}
+
+#[derive(Deserialize, Debug)]
+struct SessionInfo {
+    valid: bool,
+    sid: Option<String>,
+    /// In seconds.
+    validity: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Summary {
+    queries: Queries,
+    gravity: Gravity,
+}
+
+#[derive(Deserialize, Debug)]
+struct Queries {
+    total: usize,
+    blocked: usize,
+    percent_blocked: f32,
+    unique_domains: usize,
+    forwarded: usize,
+    cached: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct Gravity {
+    domains_being_blocked: usize,
+}
+
+/// `enabled` or `disabled`, or `failed` and `unknown` when it isn't known.
+#[derive(Deserialize, Debug)]
+struct Blocking {
+    blocking: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct History {
+    history: Vec<Slot>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Slot {
+    timestamp: f64,
+    total: u64,
+    blocked: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopDomains {
+    domains: Vec<TopDomain>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopDomain {
+    domain: String,
+    count: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopClients {
+    clients: Vec<Host>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Host {
+    ip: String,
+    /// Empty or missing when the host name is unknown.
+    name: Option<String>,
+    #[serde(default)]
+    count: u64,
+}
+
+impl Host {
+    fn name(self) -> String {
+        match self.name {
+            Some(name) if !name.is_empty() => name,
+            _ => self.ip,
+        }
+    }
+}
+
+/// The blocked and cached queries are listed as upstreams as well.
+#[derive(Deserialize, Debug)]
+struct Upstreams {
+    upstreams: Vec<Host>,
+}
+
+#[derive(Deserialize, Debug)]
+struct QueryTypes {
+    types: BTreeMap<String, u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct QueryLog {
+    queries: Vec<Query>,
+    /// The ID of the newest query, to page through the log as it was.
+    cursor: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Query {
+    id: u64,
+    domain: String,
+    client: Host,
+    status: Option<String>,
+}
+
+/// Statuses of the blocked queries: by gravity, a regex, the deny list,
+/// upstream, or for a CNAME.
+const BLOCKED: [&str; 11] = [
+    "GRAVITY",
+    "REGEX",
+    "DENYLIST",
+    "EXTERNAL_BLOCKED_IP",
+    "EXTERNAL_BLOCKED_NULL",
+    "EXTERNAL_BLOCKED_NXRA",
+    "GRAVITY_CNAME",
+    "REGEX_CNAME",
+    "DENYLIST_CNAME",
+    "SPECIAL_DOMAIN",
+    "DBBUSY",
+];
+
+/// Whether the body is an answer of `/api/auth`, telling v6 apart.
+pub fn is_auth_response(body: &str) -> bool {
+    serde_json::from_str::<Auth>(body).is_ok()
+}
+
+/// Open a session with the password, or an app password, of the web interface.
+fn login(pihole: &PiHole) -> Result<Session, PiHoleError> {
+    let password = pihole.token.clone().unwrap_or_default();
+    let body = json!({ "password": password }).to_string();
+    match pihole.send("POST", "/api/auth", &[], Some(&body))? {
+        (200, body) => {
+            let auth: Auth = serde_json::from_str(&body)?;
+            if !auth.session.valid {
+                return Err(PiHoleError::Unauthorized);
+            }
+            let validity = Duration::from_secs(auth.session.validity.max(0) as u64);
+            Ok(Session {
+                sid: auth.session.sid,
+                validity,
+                expires_at: Instant::now() + validity,
+            })
+        }
+        (401, _) => Err(PiHoleError::Unauthorized),
+        (status, _) => Err(bad_status(status, "/api/auth")),
+    }
+}
+
+/// The ID of the current session, extended as it is used, or `None` when
+/// it is missing or about to expire.
+fn current_session(pihole: &PiHole) -> Option<Option<String>> {
+    let now = Instant::now();
+    let mut state = pihole.state();
+    let session = state.session.as_mut()?;
+    if now + RENEWAL_MARGIN < session.expires_at {
+        session.expires_at = now + session.validity;
+        Some(session.sid.clone())
+    } else {
+        None
+    }
+}
+
+/// The ID of the current session, logging in again when it is about to expire.
+fn session_id(pihole: &PiHole) -> Result<Option<String>, PiHoleError> {
+    if let Some(sid) = current_session(pihole) {
+        return Ok(sid);
+    }
+    // Another thread may have logged in while this one waited.
+    let _login = pihole.login.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sid) = current_session(pihole) {
+        return Ok(sid);
+    }
+    let session = login(pihole)?;
+    let sid = session.sid.clone();
+    pihole.state().session = Some(session);
+    Ok(sid)
+}
+
+/// Forget about the session Pi-hole dropped, unless another thread already
+/// replaced it.
+fn forget_session(pihole: &PiHole, sid: &Option<String>) {
+    let mut state = pihole.state();
+    if state
+        .session
+        .as_ref()
+        .is_some_and(|session| session.sid == *sid)
+    {
+        state.session = None;
+    }
+}
+
+/// End the current session, if there is one.
+pub fn logout(pihole: &PiHole) -> Result<(), PiHoleError> {
+    let _login = pihole.login.lock().unwrap_or_else(|e| e.into_inner());
+    let sid = match pihole
+        .state()
+        .session
+        .take()
+        .and_then(|session| session.sid)
+    {
+        Some(sid) => sid,
+        None => return Ok(()),
+    };
+    match pihole.send("DELETE", "/api/auth", &[("X-FTL-SID", &sid)], None)? {
+        // Pi-hole may have dropped the session already.
+        (200..=299, _) | (401, _) | (404, _) => Ok(()),
+        (status, _) => Err(bad_status(status, "/api/auth")),
+    }
+}
+
+/// Send a request within the session. Pi-hole may have dropped it, e.g.
+/// when it restarted: the request is then sent again in a new session.
+fn request(
+    pihole: &PiHole,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+) -> Result<String, PiHoleError> {
+    let attempt = || {
+        let sid = session_id(pihole)?;
+        let headers: Vec<(&str, &str)> = sid.iter().map(|sid| ("X-FTL-SID", &sid[..])).collect();
+        let response = pihole.send(method, path, &headers, body)?;
+        Ok::<_, PiHoleError>((sid, response))
+    };
+    let response = match attempt()? {
+        (sid, (401, _)) => {
+            forget_session(pihole, &sid);
+            attempt()?.1
+        }
+        (_, response) => response,
+    };
+    match response {
+        (200..=299, body) => Ok(body),
+        (401, _) => Err(PiHoleError::Unauthorized),
+        (status, _) => Err(bad_status(status, path)),
+    }
+}
+
+fn get<T: DeserializeOwned>(pihole: &PiHole, path: &str) -> Result<T, PiHoleError> {
+    Ok(serde_json::from_str(&request(pihole, "GET", path, None)?)?)
+}
+
+pub fn status(pihole: &PiHole) -> Result<PiHoleStatus, PiHoleError> {
+    let summary: Summary = get(pihole, "/api/stats/summary")?;
+    let blocking: Blocking = get(pihole, "/api/dns/blocking")?;
+    Ok(PiHoleStatus {
+        domains_being_blocked: summary.gravity.domains_being_blocked,
+        dns_queries_today: summary.queries.total,
+        ads_blocked_today: summary.queries.blocked,
+        ads_percentage_today: summary.queries.percent_blocked,
+        unique_domains: summary.queries.unique_domains,
+        queries_forwarded: summary.queries.forwarded,
+        queries_cached: summary.queries.cached,
+        blocking: blocking.blocking != "disabled",
+    })
+}
+
+pub fn history(pihole: &PiHole) -> Result<PiHoleHistory, PiHoleError> {
+    let history: History = get(pihole, "/api/history")?;
+    let mut queries = PiHoleHistory {
+        domains_over_time: Default::default(),
+        ads_over_time: Default::default(),
+    };
+    for slot in history.history {
+        let timestamp = slot.timestamp as u64;
+        queries.domains_over_time.insert(timestamp, slot.total);
+        queries.ads_over_time.insert(timestamp, slot.blocked);
+    }
+    Ok(queries)
+}
+
+pub fn top(pihole: &PiHole, count: usize) -> Result<PiHoleTop, PiHoleError> {
+    let domains = |blocked: bool| -> Result<Vec<(String, u64)>, PiHoleError> {
+        let path = format!("/api/stats/top_domains?blocked={}&count={}", blocked, count);
+        let top: TopDomains = get(pihole, &path)?;
+        let domains = top.domains.into_iter().map(|d| (d.domain, d.count));
+        Ok(PiHoleTop::ranked(domains, count))
+    };
+    let top: TopClients = get(pihole, &format!("/api/stats/top_clients?count={}", count))?;
+    let clients = top.clients.into_iter().map(|client| {
+        let count = client.count;
+        (client.name(), count)
+    });
+    Ok(PiHoleTop {
+        blocked: domains(true)?,
+        permitted: domains(false)?,
+        clients: PiHoleTop::ranked(clients, count),
+    })
+}
+
+pub fn shares(pihole: &PiHole) -> Result<PiHoleShares, PiHoleError> {
+    let upstreams: Upstreams = get(pihole, "/api/stats/upstreams")?;
+    let upstreams = upstreams
+        .upstreams
+        .into_iter()
+        .filter(|upstream| upstream.ip != "blocklist" && upstream.ip != "cache")
+        .map(|upstream| {
+            let count = upstream.count as f32;
+            (upstream.name(), count)
+        });
+    let types: QueryTypes = get(pihole, "/api/stats/query_types")?;
+    let query_types = types
+        .types
+        .into_iter()
+        .map(|(name, count)| (name, count as f32));
+    Ok(PiHoleShares {
+        upstreams: PiHoleShares::normalized(upstreams),
+        query_types: PiHoleShares::normalized(query_types),
+    })
+}
+
+/// The log is listed newest first, each query having an increasing ID. It's
+/// read by pages of `count` queries, from a cursor on the newest one so that
+/// the pages don't move as queries come in, back to the query with the ID
+/// `after`. Only the first page is read when nothing was read before.
+pub fn queries(
+    pihole: &PiHole,
+    after: Option<u64>,
+    count: usize,
+) -> Result<Vec<(u64, PiHoleQuery)>, PiHoleError> {
+    let mut queries = vec![];
+    let mut cursor = None;
+    for _ in 0..MAX_PAGES {
+        let path = match cursor {
+            Some(cursor) => format!(
+                "/api/queries?length={}&cursor={}&start={}",
+                count,
+                cursor,
+                queries.len()
+            ),
+            None => format!("/api/queries?length={}", count),
+        };
+        let log: QueryLog = get(pihole, &path)?;
+        cursor = cursor.or(log.cursor);
+        let read = log.queries.len();
+        queries.extend(log.queries.into_iter().map(|query| {
+            let blocked = query
+                .status
+                .is_some_and(|status| BLOCKED.contains(&status.as_str()));
+            let entry = PiHoleQuery {
+                domain: query.domain,
+                client: query.client.name(),
+                blocked,
+            };
+            (query.id, entry)
+        }));
+        let caught_up = match after {
+            Some(after) => queries.iter().any(|(id, _)| *id <= after),
+            None => true,
+        };
+        if caught_up || read < count || cursor.is_none() {
+            break;
+        }
+    }
+    queries.sort_by_key(|(id, _)| *id);
+    Ok(queries)
+}
+
+pub fn set_blocking(pihole: &PiHole, disable_for: Option<Duration>) -> Result<(), PiHoleError> {
+    let body = match disable_for {
+        Some(duration) => json!({ "blocking": false, "timer": duration.as_secs() }),
+        None => json!({ "blocking": true, "timer": null }),
+    };
+    request(pihole, "POST", "/api/dns/blocking", Some(&body.to_string()))?;
+    Ok(())
+}
+
+#[test]
+fn rest_api() {
+    use super::standin::StandIn;
+    use super::Generation;
+    use std::sync::{Arc, Mutex};
+
+    // The session Pi-hole knows about, and the number of logins.
+    let sessions = Arc::new(Mutex::new((None::<String>, 0)));
+    let s = sessions.clone();
+    let server = StandIn::start(move |request| {
+        let mut sessions = s.lock().unwrap();
+        let authenticated =
+            sessions.0.is_some() && request.header("X-FTL-SID") == sessions.0.as_deref();
+        let denied = r#"{"session":{"valid":false,"totp":false,"sid":null,"validity":-1}}"#;
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/api/auth") => (401, denied.to_string()),
+            ("POST", "/api/auth") if request.body.contains("\"secret\"") => {
+                sessions.1 += 1;
+                let sid = format!("sid{}", sessions.1);
+                sessions.0 = Some(sid.clone());
+                let session = json!({ "session": {
+                    "valid": true, "totp": false, "sid": sid, "csrf": "x", "validity": 1800
+                }});
+                (200, session.to_string())
+            }
+            ("POST", "/api/auth") => (401, denied.to_string()),
+            (_, _) if !authenticated => (401, r#"{"error":{"key":"unauthorized"}}"#.to_string()),
+            ("DELETE", "/api/auth") => {
+                sessions.0 = None;
+                (204, String::new())
+            }
+            ("GET", "/api/stats/summary") => (
+                200,
+                r#"{"queries":{"total":7000,"blocked":700,"percent_blocked":10.0,
+                    "unique_domains":900,"forwarded":4000,"cached":2300,"types":{}},
+                    "clients":{"active":5,"total":8},
+                    "gravity":{"domains_being_blocked":150000,"last_update":1700000000},
+                    "took":0.001}"#
+                    .to_string(),
+            ),
+            ("GET", "/api/history") => (
+                200,
+                r#"{"history":[{"timestamp":1700000000,"total":30,"cached":5,"blocked":3,
+                    "forwarded":22},{"timestamp":1700000600.5,"total":40,"cached":5,
+                    "blocked":4,"forwarded":31}],"took":0.001}"#
+                    .to_string(),
+            ),
+            ("GET", "/api/dns/blocking") => (
+                200,
+                r#"{"blocking":"disabled","timer":25.5,"took":0.001}"#.to_string(),
+            ),
+            ("POST", "/api/dns/blocking") => (200, request.body.clone()),
+            ("GET", "/api/stats/top_domains?blocked=true&count=5") => (
+                200,
+                r#"{"domains":[{"domain":"ads.example","count":70}],"total_queries":7000,
+                    "blocked_queries":700,"took":0.001}"#
+                    .to_string(),
+            ),
+            ("GET", "/api/stats/top_domains?blocked=false&count=5") => (
+                200,
+                r#"{"domains":[{"domain":"a.example","count":300},
+                    {"domain":"b.example","count":200}],"took":0.001}"#
+                    .to_string(),
+            ),
+            ("GET", "/api/stats/upstreams") => (
+                200,
+                r#"{"upstreams":[{"ip":"blocklist","name":"blocklist","port":-1,"count":700},
+                    {"ip":"cache","name":"cache","port":-1,"count":2300},
+                    {"ip":"8.8.8.8","name":"dns.google","port":53,"count":3000},
+                    {"ip":"1.1.1.1","name":"","port":53,"count":1000}],
+                    "forwarded_queries":4000,"total_queries":7000,"took":0.001}"#
+                    .to_string(),
+            ),
+            ("GET", "/api/stats/query_types") => (
+                200,
+                r#"{"types":{"A":6000,"AAAA":1000,"ANY":0},"took":0.001}"#.to_string(),
+            ),
+            ("GET", "/api/queries?length=2") => (
+                200,
+                r#"{"queries":[{"id":42,"time":1700000001.5,"type":"A","domain":"ads.example",
+                    "client":{"ip":"192.168.1.10","name":"laptop.lan"},"status":"GRAVITY"},
+                    {"id":41,"time":1700000001.2,"type":"A","domain":"example.com",
+                    "client":{"ip":"192.168.1.11","name":null},"status":"FORWARDED"}],
+                    "cursor":42,"recordsTotal":2,"recordsFiltered":2,"took":0.001}"#
+                    .to_string(),
+            ),
+            ("GET", "/api/stats/top_clients?count=5") => (
+                200,
+                r#"{"clients":[{"ip":"192.168.1.10","name":"laptop.lan","count":900},
+                    {"ip":"192.168.1.11","name":"","count":400}],"took":0.001}"#
+                    .to_string(),
+            ),
+            _ => (404, r#"{"error":{"key":"not_found"}}"#.to_string()),
+        }
+    });
+
+    let pihole = server.client(Some("secret"));
+    assert_eq!(pihole.generation().unwrap(), Generation::V6);
+    let status = pihole.status().unwrap();
+    assert_eq!(status.dns_queries_today, 7000);
+    assert_eq!(status.domains_being_blocked, 150000);
+    assert!(!status.blocking);
+    // The session is shared by clones.
+    let history = pihole.clone().history().unwrap();
+    assert_eq!(history.domains_over_time.get(&1700000600), Some(&40));
+    let top = pihole.top(5).unwrap();
+    assert_eq!(top.blocked, vec![("ads.example".to_string(), 70)]);
+    assert_eq!(top.permitted.len(), 2);
+    assert_eq!(top.clients[1], ("192.168.1.11".to_string(), 400));
+    let shares = pihole.shares().unwrap();
+    assert_eq!(shares.upstreams[1], ("1.1.1.1".to_string(), 0.25));
+    assert_eq!(shares.query_types.len(), 2);
+    let queries = pihole.queries(&mut Default::default(), 2).unwrap();
+    assert_eq!(queries[0].client, "192.168.1.11");
+    assert!(queries[1].blocked);
+    assert_eq!(sessions.lock().unwrap().1, 1);
+
+    // Pi-hole forgot about the session.
+    sessions.lock().unwrap().0 = None;
+    pihole.set_blocking(Some(Duration::from_secs(30))).unwrap();
+    assert_eq!(sessions.lock().unwrap().1, 2);
+    let requests = server.requests();
+    let last = requests.last().unwrap();
+    assert_eq!(last.header("X-FTL-SID"), Some("sid2"));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&last.body).unwrap(),
+        json!({ "blocking": false, "timer": 30 })
+    );
+
+    // The session is ended when leaving.
+    pihole.logout().unwrap();
+    let last = server.requests().pop().unwrap();
+    assert_eq!(last.method, "DELETE");
+    assert_eq!(last.header("X-FTL-SID"), Some("sid2"));
+    assert_eq!(sessions.lock().unwrap().0, None);
+
+    match server.client(Some("wrong")).status() {
+        Err(PiHoleError::Unauthorized) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn single_session() {
+    use super::standin::StandIn;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let logins = Arc::new(AtomicUsize::new(0));
+    let l = logins.clone();
+    let server = StandIn::start(move |request| {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/api/auth") => (
+                401,
+                r#"{"session":{"valid":false,"totp":false,"sid":null,"validity":-1}}"#.to_string(),
+            ),
+            ("POST", "/api/auth") => {
+                // Slow enough for the other threads to find the session missing too.
+                thread::sleep(Duration::from_millis(100));
+                let sid = format!("sid{}", l.fetch_add(1, Ordering::SeqCst) + 1);
+                let session = json!({ "session": {
+                    "valid": true, "totp": false, "sid": sid, "csrf": "x", "validity": 1800
+                }});
+                (200, session.to_string())
+            }
+            ("GET", "/api/dns/blocking") => (
+                200,
+                r#"{"blocking":"enabled","timer":null,"took":0.001}"#.to_string(),
+            ),
+            _ => (404, r#"{"error":{"key":"not_found"}}"#.to_string()),
+        }
+    });
+    let pihole = server.client(Some("secret"));
+    pihole.generation().unwrap();
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let pihole = pihole.clone();
+            thread::spawn(move || request(&pihole, "GET", "/api/dns/blocking", None).is_ok())
+        })
+        .collect();
+    for thread in threads {
+        assert!(thread.join().unwrap());
+    }
+    assert_eq!(logins.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn query_pages() {
+    use super::standin::StandIn;
+    use super::QueryCursor;
+    use std::sync::{Arc, Mutex};
+
+    let query = |id: u64| {
+        json!({ "id": id, "time": 1700000000 + id, "type": "A",
+            "domain": format!("{}.example", id), "client": { "ip": "192.168.1.10", "name": null },
+            "status": "FORWARDED" })
+    };
+    let page = move |ids: &[u64], cursor: u64| {
+        let queries: Vec<_> = ids.iter().map(|id| query(*id)).collect();
+        json!({ "queries": queries, "cursor": cursor }).to_string()
+    };
+    let newest = Arc::new(Mutex::new(11));
+    let n = newest.clone();
+    let server = StandIn::start(move |request| {
+        let newest = *n.lock().unwrap();
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/api/auth") | ("POST", "/api/auth") => (
+                200,
+                r#"{"session":{"valid":true,"totp":false,"sid":null,"validity":1800}}"#.to_string(),
+            ),
+            ("GET", "/api/queries?length=2") => (200, page(&[newest, newest - 1], newest)),
+            ("GET", "/api/queries?length=2&cursor=15&start=2") => (200, page(&[13, 12], 15)),
+            ("GET", "/api/queries?length=2&cursor=15&start=4") => (200, page(&[11, 10], 15)),
+            _ => (404, r#"{"error":{"key":"not_found"}}"#.to_string()),
+        }
+    });
+    let pihole = server.client(None);
+    let mut cursor = QueryCursor::default();
+    let domains = |queries: Vec<PiHoleQuery>| -> Vec<String> {
+        queries.into_iter().map(|query| query.domain).collect()
+    };
+    let queries = pihole.queries(&mut cursor, 2).unwrap();
+    assert_eq!(domains(queries), vec!["10.example", "11.example"]);
+    // More queries came in than fit in a page.
+    *newest.lock().unwrap() = 15;
+    let queries = pihole.queries(&mut cursor, 2).unwrap();
+    assert_eq!(
+        domains(queries),
+        vec!["12.example", "13.example", "14.example", "15.example"]
+    );
+}
@@ -0,0 +1,333 @@
+use rustberrypi::errors::CommunicationError;
+use rustberrypi::i2c::bus::I2cBus;
+use rustberrypi::i2c::lcd::buttons::ButtonEvent;
+use rustberrypi::i2c::lcd::{AdafruitDisplay, Button};
+
+use std::time::{Duration, Instant};
+
+use crate::pihole::PiHoleQuery;
+use crate::Stats;
+
+/// A page of the status display, shown in turn with the others by a `Carousel`.
+pub trait Screen<B: I2cBus> {
+    /// Draw the screen, which has been shown for `elapsed`.
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        elapsed: Duration,
+    ) -> Result<(), CommunicationError>;
+
+    /// Keep what the screen shows from the latest statistics.
+    fn update(&mut self, _stats: &Stats) {}
+
+    /// Keep what the screen shows from the queries just answered, oldest first.
+    fn on_queries(&mut self, _queries: &[PiHoleQuery], _now: Instant) {}
+
+    /// The color the backlight should take, and until when, e.g. to flash
+    /// it. Screens are asked whether they're shown or not.
+    fn backlight(&self, _now: Instant) -> Option<((u8, u8, u8), Instant)> {
+        None
+    }
+
+    /// React to a button while the screen is shown, returning whether the
+    /// event was used. Unused events are handled by the carousel.
+    fn on_button(&mut self, _event: ButtonEvent) -> bool {
+        false
+    }
+
+    /// Whether there is something to show, screens waiting for data are skipped.
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    /// How often the screen has to be drawn again while shown, if it's animated.
+    fn refresh_interval(&self) -> Option<Duration> {
+        None
+    }
+}
+
+struct Page<B: I2cBus> {
+    screen: Box<dyn Screen<B>>,
+    duration: Duration,
+}
+
+/// Rotates through screens, each one being shown for its own duration.
+/// Left and Right skip to the previous and next screens by hand.
+///
+/// Screens are only drawn when needed: when they're first shown, after an
+/// update or a button they used, or as often as they ask to.
+pub struct Carousel<B: I2cBus> {
+    pages: Vec<Page<B>>,
+    current: usize,
+    shown_at: Instant,
+    /// When the current screen was last drawn, `None` when it has to be drawn.
+    rendered_at: Option<Instant>,
+    rotating: bool,
+    /// How long the screens are shown, in percents of their own durations.
+    pace: u32,
+    /// Drawn over the top right corner of the screens.
+    marker: Option<String>,
+}
+
+impl<B: I2cBus> Carousel<B> {
+    pub fn new() -> Self {
+        Carousel {
+            pages: vec![],
+            current: 0,
+            shown_at: Instant::now(),
+            rendered_at: None,
+            rotating: true,
+            pace: 100,
+            marker: None,
+        }
+    }
+
+    /// Show the screen for `duration` in each rotation, after the ones already added.
+    pub fn add<S: Screen<B> + 'static>(&mut self, screen: S, duration: Duration) {
+        self.pages.push(Page {
+            screen: Box::new(screen),
+            duration,
+        });
+    }
+
+    /// Whether the screens are switched automatically, otherwise only by hand.
+    pub fn set_rotating(&mut self, rotating: bool) {
+        self.rotating = rotating;
+    }
+
+    /// Show the screens for longer or shorter than their own durations, by
+    /// a percentage of them, e.g. 200 for twice as long.
+    pub fn set_pace(&mut self, percent: u32) {
+        self.pace = percent;
+    }
+
+    fn duration(&self, page: &Page<B>) -> Duration {
+        page.duration * self.pace / 100
+    }
+
+    /// Draw a few characters over the top right corner of every screen,
+    /// e.g. to tell that what they show is outdated.
+    pub fn set_marker(&mut self, marker: Option<String>) {
+        if marker != self.marker {
+            self.marker = marker;
+            self.rendered_at = None;
+        }
+    }
+
+    /// Show the screen at `index`, in the order they were added, if it's ready.
+    pub fn show(&mut self, index: usize, now: Instant) {
+        if index < self.pages.len() && self.is_ready(index) {
+            self.current = index;
+            self.restart(now);
+        }
+    }
+
+    /// Show the current screen again from the start, e.g. once something
+    /// else was drawn over it.
+    pub fn restart(&mut self, now: Instant) {
+        self.shown_at = now;
+        self.rendered_at = None;
+    }
+
+    /// Pass new statistics to all the screens. If the current screen has
+    /// nothing to show anymore, the next one is shown.
+    pub fn update(&mut self, stats: &Stats, now: Instant) {
+        for page in self.pages.iter_mut() {
+            page.screen.update(stats);
+        }
+        self.rendered_at = None;
+        if !self.is_ready(self.current) {
+            self.step(true, now);
+        }
+    }
+
+    /// Pass the queries just answered to all the screens.
+    pub fn add_queries(&mut self, queries: &[PiHoleQuery], now: Instant) {
+        for page in self.pages.iter_mut() {
+            page.screen.on_queries(queries, now);
+        }
+        self.rendered_at = None;
+    }
+
+    /// The color a screen wants for the backlight, and until when, the
+    /// first screen asking for one winning.
+    pub fn backlight(&self, now: Instant) -> Option<((u8, u8, u8), Instant)> {
+        self.pages
+            .iter()
+            .find_map(|page| page.screen.backlight(now))
+    }
+
+    /// Let the current screen handle a button, otherwise Left and Right
+    /// show the previous and next screens. Returns whether the event was used.
+    pub fn on_button(&mut self, event: ButtonEvent, now: Instant) -> bool {
+        if let Some(page) = self.pages.get_mut(self.current) {
+            if page.screen.on_button(event) {
+                self.rendered_at = None;
+                return true;
+            }
+        }
+        match event {
+            ButtonEvent::Pressed(Button::Left) => self.step(false, now),
+            ButtonEvent::Pressed(Button::Right) => self.step(true, now),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Show the next screen once the current one has been shown long
+    /// enough, and draw the current screen if needed.
+    pub fn tick(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        now: Instant,
+    ) -> Result<(), CommunicationError> {
+        let duration = match self.pages.get(self.current) {
+            Some(page) => self.duration(page),
+            None => return Ok(()),
+        };
+        if (self.rotating && now >= self.shown_at + duration) || !self.is_ready(self.current) {
+            self.step(true, now);
+        }
+
+        let page = &mut self.pages[self.current];
+        let refresh = page.screen.refresh_interval();
+        let due = match (self.rendered_at, refresh) {
+            (None, _) => true,
+            (Some(at), Some(interval)) => now >= at + interval,
+            (Some(_), None) => false,
+        };
+        if due {
+            page.screen.render(display, now - self.shown_at)?;
+            if let Some(ref marker) = self.marker {
+                let width = marker.chars().count() as u8;
+                display.set_cursor(display.geometry().cols().saturating_sub(width), 0)?;
+                display.message(marker)?;
+            }
+            self.rendered_at = Some(now);
+        }
+        Ok(())
+    }
+
+    /// When `tick` has to be called next, `None` if there's nothing to wait for.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let page = self.pages.get(self.current)?;
+        let rendered_at = match self.rendered_at {
+            Some(at) => at,
+            None => return Some(self.shown_at),
+        };
+        let switch = Some(self.shown_at + self.duration(page)).filter(|_| self.rotating);
+        let refresh = page.screen.refresh_interval().map(|interval| rendered_at + interval);
+        switch.into_iter().chain(refresh).min()
+    }
+
+    fn is_ready(&self, index: usize) -> bool {
+        self.pages[index].screen.is_ready()
+    }
+
+    /// Show the next (or previous) screen that is ready, the current one
+    /// staying if no other one is.
+    fn step(&mut self, forward: bool, now: Instant) {
+        let count = self.pages.len();
+        for i in 1..=count {
+            let index = if forward {
+                (self.current + i) % count
+            } else {
+                (self.current + count - i) % count
+            };
+            if self.is_ready(index) {
+                self.current = index;
+                break;
+            }
+        }
+        self.shown_at = now;
+        self.rendered_at = None;
+    }
+}
+
+/// A display driven through the emulated plate, for the tests.
+#[cfg(test)]
+pub(crate) fn display(
+    plate: &rustberrypi::i2c::lcd::emulator::VirtualBackplate,
+) -> AdafruitDisplay<rustberrypi::i2c::lcd::emulator::VirtualBackplate> {
+    use rustberrypi::i2c::io::MCP230xx;
+
+    AdafruitDisplay::for_backplate_with(MCP230xx::mcp23017_with_bus(plate.clone()).unwrap())
+        .unwrap()
+}
+
+#[test]
+fn rotation() {
+    use rustberrypi::i2c::lcd::emulator::VirtualBackplate;
+    use std::sync::{Arc, Mutex};
+
+    struct Named(&'static str, Arc<Mutex<bool>>);
+
+    impl<B: I2cBus> Screen<B> for Named {
+        fn render(
+            &mut self,
+            display: &mut AdafruitDisplay<B>,
+            _elapsed: Duration,
+        ) -> Result<(), CommunicationError> {
+            let mut frame = display.frame();
+            frame.write(0, 0, self.0);
+            display.render(&frame)
+        }
+
+        fn is_ready(&self) -> bool {
+            *self.1.lock().unwrap()
+        }
+
+        fn backlight(&self, now: Instant) -> Option<((u8, u8, u8), Instant)> {
+            Some(((255, 0, 0), now)).filter(|_| self.0 == "two")
+        }
+    }
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = display(&plate);
+    let ready = Arc::new(Mutex::new(false));
+    let mut carousel = Carousel::new();
+    let always = Arc::new(Mutex::new(true));
+    carousel.add(Named("one", always.clone()), Duration::from_secs(3));
+    carousel.add(Named("two", ready.clone()), Duration::from_secs(10));
+    carousel.add(Named("three", always), Duration::from_secs(5));
+
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+    let mut shown = |carousel: &mut Carousel<_>, secs| {
+        carousel.tick(&mut display, at(secs)).unwrap();
+        plate.text()[0].trim_end().to_string()
+    };
+    assert_eq!(shown(&mut carousel, 0), "one");
+    // Screens color the backlight even when they're not shown.
+    assert_eq!(carousel.backlight(at(0)), Some(((255, 0, 0), at(0))));
+    // Screens that aren't ready are skipped.
+    assert_eq!(shown(&mut carousel, 4), "three");
+    assert_eq!(carousel.next_deadline(), Some(at(9)));
+
+    *ready.lock().unwrap() = true;
+    assert_eq!(shown(&mut carousel, 9), "one");
+    assert_eq!(shown(&mut carousel, 12), "two");
+
+    // Skipping by hand shows the next screen for its whole duration.
+    assert!(carousel.on_button(ButtonEvent::Pressed(Button::Right), at(15)));
+    assert_eq!(shown(&mut carousel, 15), "three");
+    assert!(carousel.on_button(ButtonEvent::Pressed(Button::Left), at(16)));
+    assert!(carousel.on_button(ButtonEvent::Pressed(Button::Left), at(17)));
+    assert_eq!(shown(&mut carousel, 17), "one");
+    assert_eq!(shown(&mut carousel, 19), "one");
+    assert!(!carousel.on_button(ButtonEvent::Pressed(Button::Select), at(19)));
+
+    carousel.set_marker(Some("!5m".to_string()));
+    assert_eq!(shown(&mut carousel, 19), "one          !5m");
+    carousel.set_marker(None);
+    assert_eq!(shown(&mut carousel, 19), "one");
+
+    // The pace scales the duration of each screen.
+    carousel.set_pace(200);
+    assert_eq!(carousel.next_deadline(), Some(at(23)));
+
+    // Only skipping by hand switches screens when they don't rotate.
+    carousel.set_rotating(false);
+    assert_eq!(carousel.next_deadline(), None);
+    assert_eq!(shown(&mut carousel, 60), "one");
+}
@@ -0,0 +1,851 @@
+use rustberrypi::errors::CommunicationError;
+use rustberrypi::i2c::bus::I2cBus;
+use rustberrypi::i2c::lcd;
+use rustberrypi::i2c::lcd::bar::Bar;
+use rustberrypi::i2c::lcd::bignum::BigFont;
+use rustberrypi::i2c::lcd::layout;
+use rustberrypi::i2c::lcd::marquee::Marquee;
+use rustberrypi::i2c::lcd::sparkline::Sparkline;
+use rustberrypi::i2c::lcd::buttons::ButtonEvent;
+use rustberrypi::i2c::lcd::{AdafruitDisplay, Button, Frame};
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::instances::InstanceStatus;
+use crate::screen::Screen;
+use crate::pihole::{PiHoleHistory, PiHoleQuery, PiHoleStatus};
+use crate::{PiHoleError, Stats};
+
+/// Ferris, along with the name of the program.
+pub struct Ferris;
+
+impl<B: I2cBus> Screen<B> for Ferris {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let ferris = lcd::helpers::load_ferris(display);
+
+        let mut frame = display.frame();
+        let _ = write!(
+            frame,
+            "{}{}{}{} Pi-hole\n{}{}{}{} Status",
+            ferris[0], ferris[1], ferris[2], ferris[3], ferris[4], ferris[5], ferris[6], ferris[7]
+        );
+        display.render(&frame)
+    }
+}
+
+/// The number of queries of the last 24 hours, in big digits when the
+/// count fits, so it can be read from afar.
+#[derive(Default)]
+pub struct Queries {
+    status: Option<PiHoleStatus>,
+}
+
+impl<B: I2cBus> Screen<B> for Queries {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(ref status) = self.status {
+            let font = BigFont::new(display);
+            let count = status.dns_queries_today.to_string();
+            let width = font.width(&count);
+            let cols = frame.cols();
+            if width <= cols {
+                font.write(&mut frame, cols - width, 0, &count);
+            } else {
+                let text = format!("DNS last 24h {} queries", status.dns_queries_today);
+                frame.write_lines(0, 0, &layout::wrap(&text, cols));
+            }
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        self.status = Some(stats.status.clone());
+    }
+
+    fn is_ready(&self) -> bool {
+        self.status.is_some()
+    }
+}
+
+/// The number of blocked queries, with the share of them as a bar.
+#[derive(Default)]
+pub struct Blocked {
+    status: Option<PiHoleStatus>,
+}
+
+impl<B: I2cBus> Screen<B> for Blocked {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(ref status) = self.status {
+            let blocked = status.ads_blocked_today.to_string();
+            frame.write(0, 0, &layout::label_value("Blocked ads", &blocked, frame.cols()));
+            Bar::new(0, 1, frame.cols()).percentage().draw(
+                display,
+                &mut frame,
+                status.ads_percentage_today / 100.0,
+            );
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        self.status = Some(stats.status.clone());
+    }
+
+    fn is_ready(&self) -> bool {
+        self.status.is_some()
+    }
+}
+
+/// All the counters on a single page, for panels of 4 lines.
+#[derive(Default)]
+pub struct Summary {
+    status: Option<PiHoleStatus>,
+}
+
+impl<B: I2cBus> Screen<B> for Summary {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(ref status) = self.status {
+            let cols = frame.cols();
+            let counter =
+                |label: &str, count: usize| layout::label_value(label, &count.to_string(), cols);
+            frame.write(0, 0, &counter("Queries", status.dns_queries_today));
+            frame.write(0, 1, &counter("Blocked", status.ads_blocked_today));
+            Bar::new(0, 2, cols).label("Ads").percentage().draw(
+                display,
+                &mut frame,
+                status.ads_percentage_today / 100.0,
+            );
+            frame.write(0, 3, &counter("Blocklist", status.domains_being_blocked));
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        self.status = Some(stats.status.clone());
+    }
+
+    fn is_ready(&self) -> bool {
+        self.status.is_some()
+    }
+}
+
+/// How one of the instances is doing, when there are several: whether it
+/// is down or blocking is off, next to its name, and its own counters.
+pub struct Instance {
+    /// Its position in the config.
+    index: usize,
+    instance: Option<InstanceStatus>,
+}
+
+impl Instance {
+    pub fn new(index: usize) -> Self {
+        Instance {
+            index,
+            instance: None,
+        }
+    }
+}
+
+impl<B: I2cBus> Screen<B> for Instance {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(ref instance) = self.instance {
+            let cols = frame.cols();
+            let state = match instance.status {
+                _ if instance.down => "✗ down",
+                Some(ref status) if !status.blocking => "✗ off",
+                _ => "✓ on",
+            };
+            frame.write(0, 0, &layout::label_value(&instance.name, state, cols));
+            if let Some(ref status) = instance.status {
+                let counters = [
+                    ("Queries", status.dns_queries_today),
+                    ("Blocked", status.ads_blocked_today),
+                    ("Blocklist", status.domains_being_blocked),
+                ];
+                for (line, (label, count)) in (1..frame.lines()).zip(counters.iter()) {
+                    frame.write(0, line, &layout::label_value(label, &count.to_string(), cols));
+                }
+            }
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        self.instance = stats.instances.get(self.index).cloned();
+    }
+
+    fn is_ready(&self) -> bool {
+        self.instance.is_some()
+    }
+}
+
+/// The queries of the last 24 hours on the first line, and the blocked
+/// ones on the second, on the same scale.
+#[derive(Default)]
+pub struct History {
+    history: Option<PiHoleHistory>,
+}
+
+impl<B: I2cBus> Screen<B> for History {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(ref history) = self.history {
+            let queries: Vec<f32> =
+                history.domains_over_time.values().map(|n| *n as f32).collect();
+            let blocked: Vec<f32> = history.ads_over_time.values().map(|n| *n as f32).collect();
+            let max = queries.iter().cloned().fold(0.0, f32::max);
+
+            let cols = frame.cols();
+            Sparkline::new(0, 0, cols)
+                .label("DNS")
+                .max(max)
+                .draw(display, &mut frame, &queries);
+            Sparkline::new(0, 1, cols)
+                .label("Ads")
+                .max(max)
+                .draw(display, &mut frame, &blocked);
+        }
+        display.render(&frame)
+    }
+
+    /// The history is fetched along with the status, but may be missing
+    /// when that failed: the last one is kept then.
+    fn update(&mut self, stats: &Stats) {
+        if let Some(ref history) = stats.history {
+            self.history = Some(history.clone());
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.history.is_some()
+    }
+}
+
+/// Which of the top lists a `Top` screen shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TopList {
+    Blocked,
+    Permitted,
+    Clients,
+}
+
+/// Pages through a list shown a few entries at a time, under a title
+/// which is left out on panels of a single line. Up and Down show the
+/// previous and next pages, wrapping around.
+struct Pager {
+    page: usize,
+    per_page: usize,
+}
+
+impl Pager {
+    fn new() -> Self {
+        Pager {
+            page: 0,
+            per_page: 1,
+        }
+    }
+
+    fn pages(&self, len: usize) -> usize {
+        len.div_ceil(self.per_page)
+    }
+
+    /// Write the title along with the number of the page, returning the
+    /// first line left for the entries and the index of the first entry
+    /// of the page.
+    fn layout(&mut self, frame: &mut Frame, title: &str, len: usize) -> (u8, usize) {
+        let first = u8::from(frame.lines() > 1);
+        self.per_page = usize::from(frame.lines() - first);
+        self.page = self.page.min(self.pages(len).saturating_sub(1));
+        if first > 0 {
+            let page = format!("{}/{}", self.page + 1, self.pages(len));
+            let cols = frame.cols();
+            frame.write(0, 0, &layout::label_value(title, &page, cols));
+        }
+        (first, self.page * self.per_page)
+    }
+
+    fn on_button(&mut self, event: ButtonEvent, len: usize) -> bool {
+        let pages = self.pages(len);
+        match event {
+            ButtonEvent::Pressed(Button::Up) if pages > 0 => {
+                self.page = (self.page + pages - 1) % pages;
+            }
+            ButtonEvent::Pressed(Button::Down) if pages > 0 => {
+                self.page = (self.page + 1) % pages;
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// One of the top lists, with as many entries per page as there are lines
+/// below the title. Names too long for the panel scroll.
+pub struct Top {
+    list: TopList,
+    entries: Vec<(String, u64)>,
+    pager: Pager,
+    /// The page last drawn, and how long the screen had been shown then,
+    /// so that names scroll from their start on each page.
+    page_shown: Option<(usize, Duration)>,
+    scrolling: bool,
+}
+
+impl Top {
+    pub fn new(list: TopList) -> Self {
+        Top {
+            list,
+            entries: vec![],
+            pager: Pager::new(),
+            page_shown: None,
+            scrolling: false,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.list {
+            TopList::Blocked => "Top blocked",
+            TopList::Permitted => "Top domains",
+            TopList::Clients => "Top clients",
+        }
+    }
+}
+
+impl<B: I2cBus> Screen<B> for Top {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        let (cols, lines) = (frame.cols(), frame.lines());
+        let (first, start) = self.pager.layout(&mut frame, self.title(), self.entries.len());
+
+        let since = match self.page_shown {
+            Some((page, at)) if page == self.pager.page => elapsed.saturating_sub(at),
+            _ => {
+                self.page_shown = Some((self.pager.page, elapsed));
+                Duration::from_secs(0)
+            }
+        };
+        self.scrolling = false;
+        for (line, (name, count)) in (first..lines).zip(self.entries.iter().skip(start)) {
+            let count = count.to_string();
+            let width = cols.saturating_sub(count.len() as u8 + 1);
+            let marquee = Marquee::new(name, width);
+            marquee.write(&mut frame, 0, line, since);
+            frame.write(width + 1, line, &count);
+            self.scrolling |= marquee.is_scrolling();
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        if let Some(ref top) = stats.top {
+            self.entries = match self.list {
+                TopList::Blocked => top.blocked.clone(),
+                TopList::Permitted => top.permitted.clone(),
+                TopList::Clients => top.clients.clone(),
+            };
+        }
+    }
+
+    fn on_button(&mut self, event: ButtonEvent) -> bool {
+        self.pager.on_button(event, self.entries.len())
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        Some(Duration::from_millis(300)).filter(|_| self.scrolling)
+    }
+}
+
+/// The share of the queries answered from the cache, among the ones that
+/// weren't blocked.
+#[derive(Default)]
+pub struct Cache {
+    status: Option<PiHoleStatus>,
+}
+
+impl Cache {
+    fn answered(&self) -> usize {
+        self.status
+            .as_ref()
+            .map_or(0, |status| status.queries_cached + status.queries_forwarded)
+    }
+}
+
+impl<B: I2cBus> Screen<B> for Cache {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(ref status) = self.status {
+            let (cols, last) = (frame.cols(), frame.lines() - 1);
+            let counters = [
+                ("Cache hits", status.queries_cached),
+                ("Forwarded", status.queries_forwarded),
+            ];
+            for (line, (label, count)) in (0..last).zip(counters.iter()) {
+                frame.write(0, line, &layout::label_value(label, &count.to_string(), cols));
+            }
+            let bar = match last {
+                0 => Bar::new(0, 0, cols).label("Cache"),
+                _ => Bar::new(0, last, cols),
+            };
+            let ratio = status.queries_cached as f32 / self.answered() as f32;
+            bar.percentage().draw(display, &mut frame, ratio);
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        self.status = Some(stats.status.clone());
+    }
+
+    fn is_ready(&self) -> bool {
+        self.answered() > 0
+    }
+}
+
+/// Which of the splits of the queries a `Shares` screen shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShareList {
+    Upstreams,
+    QueryTypes,
+}
+
+/// How the queries split, with a bar and a percentage for each part, as
+/// many per page as there are lines below the title.
+pub struct Shares {
+    list: ShareList,
+    entries: Vec<(String, f32)>,
+    pager: Pager,
+}
+
+impl Shares {
+    pub fn new(list: ShareList) -> Self {
+        Shares {
+            list,
+            entries: vec![],
+            pager: Pager::new(),
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.list {
+            ShareList::Upstreams => "Upstreams",
+            ShareList::QueryTypes => "Query types",
+        }
+    }
+}
+
+impl<B: I2cBus> Screen<B> for Shares {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        let (cols, lines) = (frame.cols(), frame.lines());
+        let (first, start) = self.pager.layout(&mut frame, self.title(), self.entries.len());
+        // The names take the same width, for the bars to line up.
+        let width = cols * 3 / 8;
+        for (line, (name, share)) in (first..lines).zip(self.entries.iter().skip(start)) {
+            let label = layout::align(name, width, layout::Align::Left);
+            Bar::new(0, line, cols)
+                .label(&label)
+                .percentage()
+                .draw(display, &mut frame, *share);
+        }
+        display.render(&frame)
+    }
+
+    fn update(&mut self, stats: &Stats) {
+        if let Some(ref shares) = stats.shares {
+            self.entries = match self.list {
+                ShareList::Upstreams => shares.upstreams.clone(),
+                ShareList::QueryTypes => shares.query_types.clone(),
+            };
+        }
+    }
+
+    fn on_button(&mut self, event: ButtonEvent) -> bool {
+        self.pager.on_button(event, self.entries.len())
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.entries.is_empty()
+    }
+}
+
+/// The last queries answered, the newest at the bottom, with a mark telling
+/// whether they were blocked. The backlight flashes once for each blocked
+/// query, whether the screen is shown or not.
+#[derive(Default)]
+pub struct Tail {
+    queries: VecDeque<PiHoleQuery>,
+    /// When the flashes still to show start, one for each blocked query.
+    flashes: VecDeque<Instant>,
+}
+
+impl Tail {
+    /// As many queries as the largest panels have lines.
+    const LENGTH: usize = 4;
+    const FLASH: Duration = Duration::from_millis(150);
+    /// The backlight is off between flashes, to tell them apart.
+    const GAP: Duration = Duration::from_millis(100);
+    const FLASH_COLOR: (u8, u8, u8) = (255, 0, 0);
+    /// Flashes queued at most, so that they keep up with the query log
+    /// when many queries are blocked.
+    const MAX_FLASHES: usize = 8;
+}
+
+impl<B: I2cBus> Screen<B> for Tail {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        let (cols, lines) = (frame.cols(), frame.lines());
+        let client = cols / 3;
+        let columns = [
+            layout::Column::new(1, layout::Align::Left),
+            layout::Column::new(cols.saturating_sub(client + 3), layout::Align::Left),
+            layout::Column::new(client, layout::Align::Right),
+        ];
+        let shown = self.queries.len().saturating_sub(usize::from(lines));
+        for (line, query) in (0..lines).zip(self.queries.iter().skip(shown)) {
+            let mark = if query.blocked { "✗" } else { "✓" };
+            let text = layout::columns(&columns, &[mark, &query.domain, &query.client]);
+            frame.write(0, line, &text);
+        }
+        display.render(&frame)
+    }
+
+    fn on_queries(&mut self, queries: &[PiHoleQuery], now: Instant) {
+        self.queries.extend(queries.iter().cloned());
+        let extra = self.queries.len().saturating_sub(Self::LENGTH);
+        self.queries.drain(..extra);
+
+        self.flashes.retain(|start| now < *start + Self::FLASH);
+        for _ in queries.iter().filter(|query| query.blocked) {
+            if self.flashes.len() == Self::MAX_FLASHES {
+                break;
+            }
+            let start = match self.flashes.back() {
+                Some(last) => now.max(*last + Self::FLASH + Self::GAP),
+                None => now,
+            };
+            self.flashes.push_back(start);
+        }
+    }
+
+    fn backlight(&self, now: Instant) -> Option<((u8, u8, u8), Instant)> {
+        let mut flashes = self.flashes.iter().copied().peekable();
+        while let Some(start) = flashes.next() {
+            if now < start {
+                return None;
+            } else if now < start + Self::FLASH {
+                return Some((Self::FLASH_COLOR, start + Self::FLASH));
+            } else if let Some(&next) = flashes.peek().filter(|next| now < **next) {
+                return Some(((0, 0, 0), next));
+            }
+        }
+        None
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.queries.is_empty()
+    }
+}
+
+/// The time left until blocking is back on, while it is disabled.
+pub struct Countdown {
+    /// When blocking is back on, shared with the main loop which disables it.
+    until: Rc<Cell<Option<Instant>>>,
+}
+
+impl Countdown {
+    pub fn new(until: Rc<Cell<Option<Instant>>>) -> Self {
+        Countdown { until }
+    }
+
+    fn left(&self) -> Option<Duration> {
+        self.until
+            .get()
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|left| !left.is_zero())
+    }
+}
+
+impl<B: I2cBus> Screen<B> for Countdown {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        _elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        if let Some(left) = self.left() {
+            // Rounded up, so that 0:00 is never shown.
+            let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+            let left = match secs {
+                3600.. => format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+                _ => format!("{}:{:02}", secs / 60, secs % 60),
+            };
+            let cols = frame.cols();
+            if frame.lines() > 1 {
+                frame.write(0, 0, &layout::align("Blocking off", cols, layout::Align::Center));
+                let line = frame.lines() - 1;
+                frame.write(0, line, &layout::label_value("Back in", &left, cols));
+            } else {
+                frame.write(0, 0, &layout::label_value("Blocking off", &left, cols));
+            }
+        }
+        display.render(&frame)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.left().is_some()
+    }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(1))
+    }
+}
+
+/// Shown instead of the other screens once Pi-hole couldn't be reached for
+/// a while, along with the reason.
+#[derive(Default)]
+pub struct Unreachable {
+    error: String,
+}
+
+impl Unreachable {
+    const TITLE: &'static str = "Pi-hole unreachable";
+
+    pub fn set_error(&mut self, error: &PiHoleError) {
+        self.error = error.to_string();
+    }
+
+    /// The title, on as many lines as it needs, and the error on the lines
+    /// left, or scrolled on the last line when it doesn't fit.
+    fn layout(&self, cols: u8, lines: u8) -> (Vec<String>, Marquee) {
+        let last = lines - 1;
+        let mut title = layout::wrap(Self::TITLE, cols);
+        let mut error = self.error.clone();
+        if title.len() > last as usize {
+            let rest = title.split_off(last as usize).join(" ");
+            error = format!("{}: {}", rest, error);
+        }
+        let wrapped = layout::wrap(&error, cols);
+        if title.len() + wrapped.len() <= lines as usize {
+            title.extend(wrapped);
+            error.clear();
+        }
+        (title, Marquee::new(&error, cols))
+    }
+}
+
+impl<B: I2cBus> Screen<B> for Unreachable {
+    fn render(
+        &mut self,
+        display: &mut AdafruitDisplay<B>,
+        elapsed: Duration,
+    ) -> Result<(), CommunicationError> {
+        let mut frame = display.frame();
+        let last = frame.lines() - 1;
+        let (text, marquee) = self.layout(frame.cols(), frame.lines());
+        frame.write_lines(0, 0, &text);
+        if text.len() <= last as usize {
+            marquee.write(&mut frame, 0, last, elapsed);
+        }
+        display.render(&frame)
+    }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        Some(Duration::from_millis(300))
+    }
+}
+
+#[test]
+fn top_pages() {
+    use crate::pihole::PiHoleTop;
+    use rustberrypi::i2c::lcd::emulator::VirtualBackplate;
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = crate::screen::display(&plate);
+    let mut screen: Box<dyn Screen<_>> = Box::new(Top::new(TopList::Clients));
+    let clients = vec![
+        ("laptop".to_string(), 900),
+        ("living-room-tv.lan".to_string(), 400),
+        ("phone".to_string(), 12),
+    ];
+    screen.update(&Stats {
+        top: Some(PiHoleTop {
+            clients,
+            ..PiHoleTop::default()
+        }),
+        ..Stats::default()
+    });
+    let mut shown = |screen: &mut Box<dyn Screen<_>>, secs| {
+        screen.render(&mut display, Duration::from_secs(secs)).unwrap();
+        plate.text()
+    };
+    assert_eq!(shown(&mut screen, 0), vec!["Top clients  1/3", "laptop       900"]);
+    assert!(screen.on_button(ButtonEvent::Pressed(Button::Up)));
+    assert_eq!(shown(&mut screen, 5)[1], "phone         12");
+
+    // Long names scroll from their start on each page.
+    screen.on_button(ButtonEvent::Pressed(Button::Up));
+    assert_eq!(shown(&mut screen, 10)[1], "living-room- 400");
+    assert_eq!(screen.refresh_interval(), Some(Duration::from_millis(300)));
+    assert_eq!(shown(&mut screen, 12)[1], "iving-room-t 400");
+}
+
+#[test]
+fn shares() {
+    use crate::pihole::PiHoleShares;
+    use rustberrypi::i2c::lcd::emulator::VirtualBackplate;
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = crate::screen::display(&plate);
+    let mut screen: Box<dyn Screen<_>> = Box::new(Shares::new(ShareList::QueryTypes));
+    screen.update(&Stats {
+        shares: Some(PiHoleShares {
+            query_types: vec![("A".to_string(), 1.0), ("AAAA".to_string(), 0.0)],
+            ..PiHoleShares::default()
+        }),
+        ..Stats::default()
+    });
+    screen.render(&mut display, Duration::from_secs(0)).unwrap();
+    assert_eq!(plate.text(), vec!["Query types  1/2", "A      \u{ff}\u{ff}\u{ff}\u{ff} 100%"]);
+    screen.on_button(ButtonEvent::Pressed(Button::Down));
+    screen.render(&mut display, Duration::from_secs(0)).unwrap();
+    assert_eq!(plate.text()[1], "AAAA          0%");
+}
+
+#[test]
+fn instance() {
+    use rustberrypi::i2c::lcd::emulator::VirtualBackplate;
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = crate::screen::display(&plate);
+    let mut screen: Box<dyn Screen<_>> = Box::new(Instance::new(1));
+    let instance = |down: bool, blocking: bool| InstanceStatus {
+        name: "secondary".to_string(),
+        status: Some(PiHoleStatus {
+            dns_queries_today: 1000,
+            blocking,
+            ..PiHoleStatus::default()
+        }),
+        down,
+    };
+    let mut stats = Stats {
+        instances: vec![instance(false, true), instance(false, false)],
+        ..Stats::default()
+    };
+    screen.update(&stats);
+    screen.render(&mut display, Duration::from_secs(0)).unwrap();
+    assert_eq!(plate.text(), vec!["secondary  \0 off", "Queries     1000"]);
+    // The last counters of an instance that is down are still shown.
+    stats.instances[1] = instance(true, true);
+    screen.update(&stats);
+    screen.render(&mut display, Duration::from_secs(0)).unwrap();
+    assert_eq!(plate.text(), vec!["secondary \0 down", "Queries     1000"]);
+}
+
+#[test]
+fn tail() {
+    use rustberrypi::i2c::lcd::emulator::VirtualBackplate;
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = crate::screen::display(&plate);
+    let mut screen: Box<dyn Screen<_>> = Box::<Tail>::default();
+    let query = |domain: &str, client: &str, blocked| PiHoleQuery {
+        domain: domain.to_string(),
+        client: client.to_string(),
+        blocked,
+    };
+    let now = Instant::now();
+    screen.on_queries(&[query("example.com", "192.168.1.11", false)], now);
+    assert_eq!(screen.backlight(now), None);
+    screen.on_queries(
+        &[
+            query("ads.example", "laptop", true),
+            query("pi.hole", "tv", false),
+        ],
+        now,
+    );
+
+    screen.render(&mut display, Duration::from_secs(0)).unwrap();
+    // The marks and the ellipsis are custom characters.
+    let text = plate.text();
+    assert!(text[0].starts_with("\0 ads.exa\u{1} lapt"));
+    assert_eq!(text[1], "\u{2} pi.hole     tv");
+
+    screen.on_queries(&[query("tracker.example", "tv", true)], now);
+    // One flash for each blocked query, the backlight being off in between.
+    let ms = |ms| now + Duration::from_millis(ms);
+    assert_eq!(screen.backlight(ms(100)), Some(((255, 0, 0), ms(150))));
+    assert_eq!(screen.backlight(ms(200)), Some(((0, 0, 0), ms(250))));
+    assert_eq!(screen.backlight(ms(300)), Some(((255, 0, 0), ms(400))));
+    assert_eq!(screen.backlight(ms(400)), None);
+}
+
+#[test]
+fn unreachable() {
+    use std::io;
+
+    let mut screen = Unreachable::default();
+    let error = io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused");
+    screen.set_error(&PiHoleError::HttpError(error));
+    let text = |cols, lines| screen.layout(cols, lines).0;
+    assert_eq!(
+        text(20, 4),
+        vec!["Pi-hole unreachable", "Could not reach", "Pi-hole: connection", "refused"]
+    );
+    let (text, marquee) = screen.layout(16, 2);
+    assert_eq!(text, vec!["Pi-hole"]);
+    assert_eq!(
+        marquee.window(Duration::from_secs(0)),
+        "unreachable: Cou"
+    );
+}
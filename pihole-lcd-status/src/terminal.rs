@@ -0,0 +1,190 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::{cursor, queue, terminal, tty::IsTty};
+
+use rustberrypi::i2c::io::MCP230xx;
+use rustberrypi::i2c::lcd::emulator::{Hd44780, VirtualBackplate};
+use rustberrypi::i2c::lcd::{AdafruitDisplay, Button, CharacterRom, DisplayBuilder, Geometry};
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::PiHoleError;
+
+/// How long a button stays pressed after a key stroke, terminals don't
+/// report key releases.
+const PRESS_DURATION: Duration = Duration::from_millis(300);
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Whether the terminal panel can be used, e.g. we're attached to a terminal.
+pub fn is_available() -> bool {
+    io::stdout().is_tty()
+}
+
+/// Start an emulated LCD plate with a panel of the given size drawn in the
+/// terminal, and return a display driving it. The arrow keys and Enter (or
+/// Space) act as the plate buttons, q or Ctrl-C calls `quit`.
+pub fn start<F>(
+    geometry: Geometry,
+    quit: F,
+) -> Result<AdafruitDisplay<VirtualBackplate>, PiHoleError>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let plate = VirtualBackplate::new(geometry.cols(), geometry.lines());
+    let display =
+        DisplayBuilder::new(geometry).build(MCP230xx::mcp23017_with_bus(plate.clone())?)?;
+
+    terminal::enable_raw_mode().map_err(PiHoleError::TerminalError)?;
+    queue!(
+        io::stdout(),
+        terminal::EnterAlternateScreen,
+        cursor::Hide,
+        terminal::Clear(terminal::ClearType::All)
+    )
+    .map_err(PiHoleError::TerminalError)?;
+
+    thread::spawn(move || {
+        match run(&plate) {
+            Ok(()) => quit(),
+            Err(e) => {
+                restore();
+                eprintln!("Terminal panel stopped: {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+    Ok(display)
+}
+
+/// Give the terminal back, as it was before `start`.
+pub fn restore() {
+    let _ = queue!(
+        io::stdout(),
+        ResetColor,
+        cursor::Show,
+        terminal::LeaveAlternateScreen
+    );
+    let _ = io::stdout().flush();
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Draw the panel and press the buttons from the keys, until q or Ctrl-C.
+fn run(plate: &VirtualBackplate) -> io::Result<()> {
+    let mut pressed: Option<(Button, Instant)> = None;
+    loop {
+        draw(plate)?;
+
+        if event::poll(REFRESH_INTERVAL)? {
+            if let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            {
+                let button = match code {
+                    KeyCode::Char('q') => None,
+                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => None,
+                    KeyCode::Up => Some(Button::Up),
+                    KeyCode::Down => Some(Button::Down),
+                    KeyCode::Left => Some(Button::Left),
+                    KeyCode::Right => Some(Button::Right),
+                    KeyCode::Enter | KeyCode::Char(' ') => Some(Button::Select),
+                    _ => continue,
+                };
+                match button {
+                    Some(button) => {
+                        if let Some((previous, _)) = pressed.take() {
+                            plate.release(previous);
+                        }
+                        plate.press(button);
+                        pressed = Some((button, Instant::now()));
+                    }
+                    None => return Ok(()),
+                }
+            }
+        }
+
+        if let Some((button, since)) = pressed.take() {
+            if since.elapsed() >= PRESS_DURATION {
+                plate.release(button);
+            } else {
+                pressed = Some((button, since));
+            }
+        }
+    }
+}
+
+/// Draw the panel, each character cell being 5x8 pixels wide drawn with
+/// half blocks. Custom characters are drawn pixel by pixel, characters from
+/// the ROM use the terminal font, assuming the plate has the A00 ROM.
+fn draw(plate: &VirtualBackplate) -> io::Result<()> {
+    let lcd = plate.lcd();
+    let (r, g, b) = plate.backlight();
+    let on = |lit| if lit { 255 } else { 0 };
+    let pixel = if (r, g, b) == (false, false, false) {
+        Color::Rgb {
+            r: 60,
+            g: 60,
+            b: 60,
+        }
+    } else {
+        Color::Rgb {
+            r: on(r),
+            g: on(g),
+            b: on(b),
+        }
+    };
+
+    let mut text = lcd.text();
+    if !lcd.display_control().display {
+        for line in text.iter_mut() {
+            *line = " ".repeat(line.len());
+        }
+    }
+
+    let mut stdout = io::stdout();
+    queue!(stdout, cursor::MoveTo(0, 0), SetForegroundColor(pixel))?;
+    for (row, line) in text.iter().enumerate() {
+        for y in 0..4 {
+            let cells: Vec<String> = line.chars().map(|c| cell(&lcd, c, y)).collect();
+            queue!(
+                stdout,
+                cursor::MoveTo(0, (row * 5 + y) as u16),
+                Print(cells.join(" "))
+            )?;
+        }
+    }
+    queue!(
+        stdout,
+        ResetColor,
+        cursor::MoveTo(0, (text.len() * 5) as u16),
+        Print("arrows/enter: buttons, q: quit")
+    )?;
+    stdout.flush()
+}
+
+/// One terminal row of a character cell, covering pixel rows `2 * y` and `2 * y + 1`.
+fn cell(lcd: &Hd44780, c: char, y: usize) -> String {
+    let code = c as u32;
+    if code < 16 {
+        let glyph = lcd.glyph(code as u8);
+        let (top, bottom) = (glyph[2 * y], glyph[2 * y + 1]);
+        (0..5)
+            .rev()
+            .map(|x| match (top >> x & 1, bottom >> x & 1) {
+                (1, 1) => '█',
+                (1, 0) => '▀',
+                (0, 1) => '▄',
+                _ => ' ',
+            })
+            .collect()
+    } else if y == 1 {
+        let c = CharacterRom::A00.decode(code as u8).unwrap_or('?');
+        format!("  {}  ", c)
+    } else {
+        "     ".to_string()
+    }
+}
@@ -1,5 +1,6 @@
 use bmp;
-use rppal::i2c;
+use embedded_hal::{digital, i2c as hal_i2c};
+use rppal::{gpio, i2c};
 
 use std::fmt;
 use std::error;
@@ -7,15 +8,39 @@ use std::error;
 #[derive(Debug)]
 pub enum CommunicationError {
     BusError(i2c::Error),
+    HalError(hal_i2c::ErrorKind),
+    GpioError(gpio::Error),
     WrongPin(u8),
+    WrongAddress(u16),
     BitmapError(bmp::BmpError),
+    /// A frame needs more custom characters than the panel can hold.
+    TooManyGlyphs(usize),
+    /// The cursor was moved out of the panel, to a column and line.
+    CursorOutOfRange(u8, u8),
     ReadingError,
     WritingError,
 }
 
 impl fmt::Display for CommunicationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "invalid first item to double")
+        match self {
+            CommunicationError::BusError(e) => write!(f, "I2C bus error: {}", e),
+            CommunicationError::HalError(kind) => write!(f, "I2C error: {}", kind),
+            CommunicationError::GpioError(e) => write!(f, "GPIO error: {}", e),
+            CommunicationError::WrongPin(pin) => write!(f, "no pin {} on the expander", pin),
+            CommunicationError::WrongAddress(address) => {
+                write!(f, "invalid 7-bit I2C address {:#x}", address)
+            }
+            CommunicationError::BitmapError(e) => write!(f, "invalid bitmap: {}", e),
+            CommunicationError::TooManyGlyphs(count) => {
+                write!(f, "{} custom characters needed, the panel holds 8", count)
+            }
+            CommunicationError::CursorOutOfRange(col, line) => {
+                write!(f, "column {} of line {} is out of the panel", col, line)
+            }
+            CommunicationError::ReadingError => write!(f, "could not read from the device"),
+            CommunicationError::WritingError => write!(f, "could not write to the device"),
+        }
     }
 }
 
@@ -25,8 +50,14 @@ impl error::Error for CommunicationError {
         "there was a communication error with a device connected to the Raspberry Pi."
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         // Generic error, underlying cause isn't tracked.
         None
     }
 }
+
+impl digital::Error for CommunicationError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
@@ -0,0 +1,125 @@
+use embedded_hal::i2c::{Error, I2c};
+
+use std::cell::RefCell;
+
+use crate::errors::CommunicationError;
+use crate::i2c::bus::I2cBus;
+
+/// Adapts any [embedded-hal](https://docs.rs/embedded-hal) I2C implementation
+/// so the drivers of this crate can run on top of it, e.g. on other Linux
+/// boards through `linux-embedded-hal`.
+pub struct HalBus<I: I2c> {
+    i2c: RefCell<I>,
+    address: u8,
+}
+
+impl<I: I2c> HalBus<I> {
+    pub fn new(i2c: I) -> Self {
+        Self {
+            i2c: RefCell::new(i2c),
+            address: 0,
+        }
+    }
+
+    /// Give back the wrapped I2C implementation.
+    pub fn release(self) -> I {
+        self.i2c.into_inner()
+    }
+}
+
+fn hal_error<E: Error>(e: E) -> CommunicationError {
+    CommunicationError::HalError(e.kind())
+}
+
+impl<I: I2c> I2cBus for HalBus<I> {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), CommunicationError> {
+        if address > 0x7F {
+            return Err(CommunicationError::WrongAddress(address));
+        }
+        self.address = address as u8;
+        Ok(())
+    }
+
+    fn block_write(&self, command: u8, buffer: &[u8]) -> Result<(), CommunicationError> {
+        let mut data = Vec::with_capacity(buffer.len() + 1);
+        data.push(command);
+        data.extend_from_slice(buffer);
+        self.i2c
+            .borrow_mut()
+            .write(self.address, &data)
+            .map_err(hal_error)
+    }
+
+    fn block_read(&self, command: u8, buffer: &mut [u8]) -> Result<(), CommunicationError> {
+        self.i2c
+            .borrow_mut()
+            .write_read(self.address, &[command], buffer)
+            .map_err(hal_error)
+    }
+
+    fn smbus_send_byte(&self, value: u8) -> Result<(), CommunicationError> {
+        self.i2c
+            .borrow_mut()
+            .write(self.address, &[value])
+            .map_err(hal_error)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, CommunicationError> {
+        self.i2c
+            .get_mut()
+            .write(self.address, buffer)
+            .map_err(hal_error)?;
+        Ok(buffer.len())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, CommunicationError> {
+        self.i2c
+            .get_mut()
+            .read(self.address, buffer)
+            .map_err(hal_error)?;
+        Ok(buffer.len())
+    }
+}
+
+#[cfg(test)]
+struct Recorder(Vec<(u8, Vec<u8>)>);
+
+#[cfg(test)]
+impl embedded_hal::i2c::ErrorType for Recorder {
+    type Error = embedded_hal::i2c::ErrorKind;
+}
+
+#[cfg(test)]
+impl I2c for Recorder {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        use embedded_hal::i2c::Operation;
+
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => self.0.push((address, data.to_vec())),
+                Operation::Read(buffer) => buffer.iter_mut().for_each(|b| *b = address),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn hal_bus() {
+    let mut bus = HalBus::new(Recorder(vec![]));
+    assert!(bus.set_slave_address(0x80).is_err());
+    bus.set_slave_address(0x20).unwrap();
+    bus.block_write(0x12, &[0xAB]).unwrap();
+
+    let mut data = [0; 2];
+    bus.block_read(0x12, &mut data).unwrap();
+    assert_eq!(data, [0x20, 0x20]);
+    assert_eq!(
+        bus.release().0,
+        [(0x20, vec![0x12, 0xAB]), (0x20, vec![0x12])]
+    );
+}
@@ -0,0 +1,226 @@
+use rppal::i2c;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::rc::Rc;
+
+use crate::errors::CommunicationError;
+use crate::i2c::bus::{DefaultBus, I2cBus};
+
+/// A single operation recorded by the [`MockBus`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transaction {
+    SetSlaveAddress(u16),
+    BlockWrite {
+        address: u16,
+        command: u8,
+        data: Vec<u8>,
+    },
+    BlockRead {
+        address: u16,
+        command: u8,
+        len: usize,
+    },
+    SendByte {
+        address: u16,
+        value: u8,
+    },
+    Write {
+        address: u16,
+        data: Vec<u8>,
+    },
+    Read {
+        address: u16,
+        len: usize,
+    },
+}
+
+#[derive(Default)]
+struct State {
+    address: u16,
+    registers: HashMap<u16, Vec<u8>>,
+    reads: VecDeque<Vec<u8>>,
+    failures: usize,
+    transactions: Vec<Transaction>,
+}
+
+impl State {
+    fn registers(&mut self) -> &mut Vec<u8> {
+        self.registers
+            .entry(self.address)
+            .or_insert_with(|| vec![0; 256])
+    }
+
+    /// Record the transaction, and fail it if a failure was scripted.
+    fn record(&mut self, transaction: Transaction) -> Result<(), CommunicationError> {
+        self.transactions.push(transaction);
+        if self.failures > 0 {
+            self.failures -= 1;
+            Err(CommunicationError::BusError(i2c::Error::Io(
+                io::Error::other("scripted mock failure"),
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// In-memory I2C bus, used to exercise the drivers without any hardware.
+///
+/// Each slave address is backed by a 256 bytes register file: block writes
+/// store data in it and block reads return it, which is enough to emulate
+/// simple register based devices such as the MCP23017. Raw reads are served
+/// from a queue of scripted responses, and failures can be injected.
+///
+/// The bus is a cheap handle on shared state, so a test can keep a clone
+/// to inspect the recorded transactions after handing the bus to a driver.
+#[derive(Clone, Default)]
+pub struct MockBus {
+    state: Rc<RefCell<State>>,
+}
+
+impl MockBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Preload the register file of the device at `address`, from `command` onwards.
+    pub fn set_registers(&self, address: u16, command: u8, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        let registers = state
+            .registers
+            .entry(address)
+            .or_insert_with(|| vec![0; 256]);
+        for (i, value) in data.iter().enumerate() {
+            registers[(command as usize + i) % 256] = *value;
+        }
+    }
+
+    /// Return the content of the register file of the device at `address`.
+    pub fn registers(&self, address: u16) -> Vec<u8> {
+        self.state
+            .borrow()
+            .registers
+            .get(&address)
+            .cloned()
+            .unwrap_or_else(|| vec![0; 256])
+    }
+
+    /// Queue the data returned by the next raw `read`.
+    pub fn queue_read(&self, data: &[u8]) {
+        self.state.borrow_mut().reads.push_back(data.to_vec());
+    }
+
+    /// Make the next `count` operations fail with a bus error.
+    pub fn fail_next(&self, count: usize) {
+        self.state.borrow_mut().failures = count;
+    }
+
+    /// All the transactions recorded so far, in order.
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.state.borrow().transactions.clone()
+    }
+
+    /// Forget about the transactions recorded so far.
+    pub fn clear_transactions(&self) {
+        self.state.borrow_mut().transactions.clear();
+    }
+}
+
+impl I2cBus for MockBus {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), CommunicationError> {
+        let mut state = self.state.borrow_mut();
+        state.record(Transaction::SetSlaveAddress(address))?;
+        state.address = address;
+        Ok(())
+    }
+
+    fn block_write(&self, command: u8, buffer: &[u8]) -> Result<(), CommunicationError> {
+        let mut state = self.state.borrow_mut();
+        let address = state.address;
+        state.record(Transaction::BlockWrite {
+            address,
+            command,
+            data: buffer.to_vec(),
+        })?;
+        let registers = state.registers();
+        for (i, value) in buffer.iter().enumerate() {
+            registers[(command as usize + i) % 256] = *value;
+        }
+        Ok(())
+    }
+
+    fn block_read(&self, command: u8, buffer: &mut [u8]) -> Result<(), CommunicationError> {
+        let mut state = self.state.borrow_mut();
+        let address = state.address;
+        state.record(Transaction::BlockRead {
+            address,
+            command,
+            len: buffer.len(),
+        })?;
+        let registers = state.registers();
+        for (i, value) in buffer.iter_mut().enumerate() {
+            *value = registers[(command as usize + i) % 256];
+        }
+        Ok(())
+    }
+
+    fn smbus_send_byte(&self, value: u8) -> Result<(), CommunicationError> {
+        let mut state = self.state.borrow_mut();
+        let address = state.address;
+        state.record(Transaction::SendByte { address, value })
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, CommunicationError> {
+        let mut state = self.state.borrow_mut();
+        let address = state.address;
+        state.record(Transaction::Write {
+            address,
+            data: buffer.to_vec(),
+        })?;
+        Ok(buffer.len())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, CommunicationError> {
+        let mut state = self.state.borrow_mut();
+        let address = state.address;
+        state.record(Transaction::Read {
+            address,
+            len: buffer.len(),
+        })?;
+        let data = state.reads.pop_front().unwrap_or_default();
+        let len = data.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+}
+
+impl DefaultBus for MockBus {
+    /// A fresh bus, with blank register files and no scripted reads.
+    fn open() -> Result<Self, CommunicationError> {
+        Ok(MockBus::new())
+    }
+}
+
+#[test]
+fn register_file() {
+    let mut bus = MockBus::new();
+    bus.set_slave_address(0x20).unwrap();
+    bus.block_write(0x12, &[0xAB, 0xCD]).unwrap();
+
+    let mut data = [0; 2];
+    bus.block_read(0x12, &mut data).unwrap();
+    assert_eq!(data, [0xAB, 0xCD]);
+    assert_eq!(bus.registers(0x20)[0x13], 0xCD);
+    assert_eq!(bus.registers(0x21)[0x13], 0x00);
+}
+
+#[test]
+fn scripted_failures() {
+    let mut bus = MockBus::new();
+    bus.fail_next(1);
+    assert!(bus.write(&[0x00]).is_err());
+    assert_eq!(bus.write(&[0x00]).unwrap(), 1);
+    assert_eq!(bus.transactions().len(), 2);
+}
@@ -0,0 +1,75 @@
+use rppal::i2c::I2c;
+
+use crate::errors::CommunicationError;
+
+pub mod hal;
+pub mod mock;
+
+/// The subset of I2C/SMBus operations the drivers in this crate rely on.
+///
+/// Every driver is generic over this trait, which lets them run on top of
+/// the Raspberry Pi I2C peripheral (through `rppal`), any embedded-hal I2C
+/// implementation (through [`hal::HalBus`]) as well as against the
+/// in-memory [`mock::MockBus`] when no hardware is around.
+pub trait I2cBus {
+    /// Set the address of the slave device all subsequent operations target.
+    fn set_slave_address(&mut self, address: u16) -> Result<(), CommunicationError>;
+
+    /// Send `buffer` to the slave, prefixed by the `command` (register) byte.
+    fn block_write(&self, command: u8, buffer: &[u8]) -> Result<(), CommunicationError>;
+
+    /// Fill `buffer` with data read from the slave, starting at `command`.
+    fn block_read(&self, command: u8, buffer: &mut [u8]) -> Result<(), CommunicationError>;
+
+    /// Send a single byte to the slave, without a command byte.
+    fn smbus_send_byte(&self, value: u8) -> Result<(), CommunicationError>;
+
+    /// Raw write, returns the number of bytes written.
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, CommunicationError>;
+
+    /// Raw read, returns the number of bytes read.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, CommunicationError>;
+}
+
+/// A bus with a default to open, for drivers set up without being given one.
+pub trait DefaultBus: I2cBus + Sized {
+    /// Open the default bus of this kind.
+    fn open() -> Result<Self, CommunicationError>;
+}
+
+impl I2cBus for I2c {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), CommunicationError> {
+        I2c::set_slave_address(self, address).map_err(CommunicationError::BusError)
+    }
+
+    fn block_write(&self, command: u8, buffer: &[u8]) -> Result<(), CommunicationError> {
+        I2c::block_write(self, command, buffer).map_err(CommunicationError::BusError)
+    }
+
+    fn block_read(&self, command: u8, buffer: &mut [u8]) -> Result<(), CommunicationError> {
+        I2c::block_read(self, command, buffer).map_err(CommunicationError::BusError)
+    }
+
+    fn smbus_send_byte(&self, value: u8) -> Result<(), CommunicationError> {
+        I2c::smbus_send_byte(self, value).map_err(CommunicationError::BusError)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, CommunicationError> {
+        I2c::write(self, buffer).map_err(CommunicationError::BusError)
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, CommunicationError> {
+        I2c::read(self, buffer).map_err(CommunicationError::BusError)
+    }
+}
+
+impl DefaultBus for I2c {
+    fn open() -> Result<Self, CommunicationError> {
+        open()
+    }
+}
+
+/// Open the default Raspberry Pi I2C bus.
+pub fn open() -> Result<I2c, CommunicationError> {
+    I2c::new().map_err(CommunicationError::BusError)
+}
@@ -2,18 +2,44 @@ use rppal::gpio::{Level, Mode, PullUpDown};
 use rppal::i2c::I2c;
 
 use crate::errors::CommunicationError;
+use crate::i2c::bus::{self, I2cBus};
 
-pub struct MCP230xx {
-    device: I2c,
+pub mod pin;
+
+pub struct MCP230xx<B: I2cBus = I2c> {
+    device: B,
     num_gpio: u8,
     iodir: Vec<u8>,
     gppu: Vec<u8>,
     gpio: Vec<u8>,
+    gpinten: Vec<u8>,
+    defval: Vec<u8>,
+    intcon: Vec<u8>,
 }
 
 const IODIR: u8 = 0x00;
-const GPIO: u8 = 0x12;
+const GPINTEN: u8 = 0x04;
+const DEFVAL: u8 = 0x06;
+const INTCON: u8 = 0x08;
+const IOCON: u8 = 0x0A;
 const GPPU: u8 = 0x0C;
+const INTF: u8 = 0x0E;
+const INTCAP: u8 = 0x10;
+const GPIO: u8 = 0x12;
+
+// IOCON flags
+const IOCON_MIRROR: u8 = 0x40;
+const IOCON_ODR: u8 = 0x04;
+const IOCON_INTPOL: u8 = 0x02;
+
+/// What makes a pin raise an interrupt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interrupt {
+    /// Any change of the pin level, compared to its previous value.
+    OnChange,
+    /// The pin level differs from the given default value.
+    Compare(Level),
+}
 
 pub enum Pin {
     Up,
@@ -21,44 +47,41 @@ pub enum Pin {
 }
 
 /// MCP230xx series GPIO extender
-impl MCP230xx {
-    fn new(address: u8, num_gpio: u8) -> Result<Self, CommunicationError> {
+impl<B: I2cBus> MCP230xx<B> {
+    /// Talk to the extender at `address` through the given I2C bus.
+    pub fn with_bus(mut bus: B, address: u8, num_gpio: u8) -> Result<Self, CommunicationError> {
         // Assume starting in ICON.BANK = 0 mode (sequential access).
         // Compute how many bytes are needed to store count of GPIO.
         let gpio_bytes = (num_gpio as f32 / 8.0).ceil() as usize;
 
         // Buffer register values so they can be changed without reading.
-        let mut iodir = vec![];
-        let mut gpio = vec![];
-        let mut gppu = vec![];
-        for _ in 0..gpio_bytes {
-            iodir.push(0xFF);
-            gpio.push(0x00);
-            gppu.push(0x00);
-        }
+        let iodir = vec![0xFF; gpio_bytes];
+        let gpio = vec![0x00; gpio_bytes];
+        let gppu = vec![0x00; gpio_bytes];
 
-        let mut i2c_device = I2c::new().unwrap();
-        i2c_device
-            .set_slave_address(address as u16)
-            .map_err(|e| return CommunicationError::BusError(e))?;
+        bus.set_slave_address(u16::from(address))?;
 
         let device = Self {
-            device: i2c_device,
+            device: bus,
             num_gpio,
             iodir,
             gpio,
             gppu,
+            gpinten: vec![0x00; gpio_bytes],
+            defval: vec![0x00; gpio_bytes],
+            intcon: vec![0x00; gpio_bytes],
         };
 
-        // Write current direction and pullup buffer state.
+        // Write current direction, pullup and interrupt buffer state.
         device.write_iodir()?;
         device.write_gppu()?;
+        device.write_interrupts()?;
         Ok(device)
     }
 
-    /// MCP23017-based GPIO class with 16 GPIO pins.
-    pub fn for_mcp23017() -> Result<Self, CommunicationError> {
-        Self::new(0x20, 16)
+    /// MCP23017-based GPIO class with 16 GPIO pins, on the given I2C bus.
+    pub fn mcp23017_with_bus(bus: B) -> Result<Self, CommunicationError> {
+        Self::with_bus(bus, 0x20, 16)
     }
 
     /// Checks that a pin is addressable, e.g. that the index is lower
@@ -98,21 +121,21 @@ impl MCP230xx {
     /// Write the specified byte value to the IODIR registor.
     /// If no value specified the current buffered value will be written.
     fn write_iodir(&self) -> Result<(), CommunicationError> {
-        self.device
-            .block_write(IODIR, &self.iodir)
-            .map_err(|e| CommunicationError::BusError(e))
+        self.device.block_write(IODIR, &self.iodir)
     }
 
     fn write_gppu(&self) -> Result<(), CommunicationError> {
-        self.device
-            .block_write(GPPU, &self.gppu)
-            .map_err(|e| CommunicationError::BusError(e))
+        self.device.block_write(GPPU, &self.gppu)
+    }
+
+    fn write_interrupts(&self) -> Result<(), CommunicationError> {
+        self.device.block_write(DEFVAL, &self.defval)?;
+        self.device.block_write(INTCON, &self.intcon)?;
+        self.device.block_write(GPINTEN, &self.gpinten)
     }
 
     fn write_gpio(&self) -> Result<(), CommunicationError> {
-        self.device
-            .block_write(GPIO, &self.gpio)
-            .map_err(|e| CommunicationError::BusError(e))
+        self.device.block_write(GPIO, &self.gpio)
     }
 
     pub fn output(&mut self, pin: u8, value: bool) -> Result<(), CommunicationError> {
@@ -135,10 +158,10 @@ impl MCP230xx {
 
     /// Read multiple pins specified in the given list and return list of pin values
     pub fn input_pins(&mut self, pins: &[u8]) -> Result<Vec<Level>, CommunicationError> {
-        let _ = pins.iter().map(|p| self.validate_pin(*p));
-        self.device
-            .block_read(GPIO, &mut self.gpio)
-            .map_err(|e| CommunicationError::BusError(e))?;
+        for pin in pins {
+            self.validate_pin(*pin)?;
+        }
+        self.device.block_read(GPIO, &mut self.gpio)?;
 
         Ok(pins
             .iter()
@@ -156,4 +179,137 @@ impl MCP230xx {
     pub fn input(&mut self, pin: u8) -> Result<Level, CommunicationError> {
         Ok(self.input_pins(&[pin])?[0])
     }
+
+    /// Configure how the INTA/INTB lines behave. When mirrored, both lines
+    /// are raised for any pin of the extender. Open-drain outputs need an
+    /// external pull-up, otherwise the lines are driven high or low
+    /// depending on the polarity.
+    pub fn configure_interrupt_output(
+        &mut self,
+        mirror: bool,
+        open_drain: bool,
+        active_high: bool,
+    ) -> Result<(), CommunicationError> {
+        let mut iocon = 0;
+        if mirror {
+            iocon |= IOCON_MIRROR;
+        }
+        if open_drain {
+            iocon |= IOCON_ODR;
+        }
+        if active_high {
+            iocon |= IOCON_INTPOL;
+        }
+        self.device.block_write(IOCON, &[iocon])
+    }
+
+    /// Raise an interrupt when the given pin matches the trigger condition.
+    pub fn enable_interrupt(
+        &mut self,
+        pin: u8,
+        trigger: Interrupt,
+    ) -> Result<(), CommunicationError> {
+        self.validate_pin(pin)?;
+        let idx = (pin / 8) as usize;
+        let bit = 1 << (pin % 8);
+        match trigger {
+            Interrupt::OnChange => self.intcon[idx] &= !bit,
+            Interrupt::Compare(level) => {
+                self.intcon[idx] |= bit;
+                if level == Level::High {
+                    self.defval[idx] |= bit;
+                } else {
+                    self.defval[idx] &= !bit;
+                }
+            }
+        }
+        self.gpinten[idx] |= bit;
+        self.write_interrupts()
+    }
+
+    pub fn disable_interrupt(&mut self, pin: u8) -> Result<(), CommunicationError> {
+        self.validate_pin(pin)?;
+        self.gpinten[(pin / 8) as usize] &= !(1 << (pin % 8));
+        self.device.block_write(GPINTEN, &self.gpinten)
+    }
+
+    /// Return the pins that raised the pending interrupt, if any.
+    pub fn interrupt_flags(&mut self) -> Result<Vec<u8>, CommunicationError> {
+        let mut intf = vec![0; self.gpio.len()];
+        self.device.block_read(INTF, &mut intf)?;
+        Ok((0..self.num_gpio)
+            .filter(|pin| intf[(pin / 8) as usize] & 1 << (pin % 8) > 0)
+            .collect())
+    }
+
+    /// Return the level the given pins had when the interrupt was raised.
+    /// Reading the captured levels clears the interrupt.
+    pub fn interrupt_capture(&mut self, pins: &[u8]) -> Result<Vec<Level>, CommunicationError> {
+        for pin in pins {
+            self.validate_pin(*pin)?;
+        }
+        let mut intcap = vec![0; self.gpio.len()];
+        self.device.block_read(INTCAP, &mut intcap)?;
+
+        Ok(pins
+            .iter()
+            .map(|pin| {
+                if intcap[(pin / 8) as usize] & 1 << (pin % 8) > 0 {
+                    Level::High
+                } else {
+                    Level::Low
+                }
+            })
+            .collect())
+    }
+}
+
+impl MCP230xx<I2c> {
+    /// MCP23017-based GPIO class with 16 GPIO pins.
+    pub fn for_mcp23017() -> Result<Self, CommunicationError> {
+        Self::mcp23017_with_bus(bus::open()?)
+    }
+}
+
+#[test]
+fn interrupts() {
+    use crate::i2c::bus::mock::MockBus;
+
+    let bus = MockBus::new();
+    let mut mcp = MCP230xx::mcp23017_with_bus(bus.clone()).unwrap();
+    mcp.enable_interrupt(0, Interrupt::OnChange).unwrap();
+    mcp.enable_interrupt(9, Interrupt::Compare(Level::High)).unwrap();
+    let registers = bus.registers(0x20);
+    assert_eq!(registers[GPINTEN as usize..][..2], [0x01, 0x02]);
+    assert_eq!(registers[INTCON as usize..][..2], [0x00, 0x02]);
+    assert_eq!(registers[DEFVAL as usize..][..2], [0x00, 0x02]);
+
+    mcp.disable_interrupt(0).unwrap();
+    assert_eq!(bus.registers(0x20)[GPINTEN as usize], 0x00);
+
+    bus.set_registers(0x20, INTF, &[0x00, 0x02]);
+    bus.set_registers(0x20, INTCAP, &[0x01, 0x00]);
+    assert_eq!(mcp.interrupt_flags().unwrap(), [9]);
+    assert_eq!(
+        mcp.interrupt_capture(&[0, 9]).unwrap(),
+        [Level::High, Level::Low]
+    );
+}
+
+#[test]
+fn output_and_input() {
+    use crate::i2c::bus::mock::MockBus;
+
+    let bus = MockBus::new();
+    let mut mcp = MCP230xx::mcp23017_with_bus(bus.clone()).unwrap();
+    mcp.setup(9, Mode::Output).unwrap();
+    assert_eq!(bus.registers(0x20)[IODIR as usize..][..2], [0xFF, 0xFD]);
+
+    mcp.output_pins(&[(1, true), (9, true)]).unwrap();
+    assert_eq!(bus.registers(0x20)[GPIO as usize..][..2], [0x02, 0x02]);
+
+    bus.set_registers(0x20, GPIO, &[0x00, 0x80]);
+    assert_eq!(mcp.input(15).unwrap(), Level::High);
+    assert_eq!(mcp.input(1).unwrap(), Level::Low);
+    assert!(mcp.input(16).is_err());
 }
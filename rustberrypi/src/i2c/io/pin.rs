@@ -0,0 +1,100 @@
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+use rppal::gpio::{Level, Mode, PullUpDown};
+
+use std::cell::RefCell;
+
+use crate::errors::CommunicationError;
+use crate::i2c::bus::I2cBus;
+use crate::i2c::io::MCP230xx;
+
+/// Handle on a single pin of a MCP230xx extender, implementing the
+/// embedded-hal digital traits. The extender is shared between the
+/// handles through a `RefCell`.
+pub struct McpPin<'a, B: I2cBus> {
+    mcp: &'a RefCell<MCP230xx<B>>,
+    pin: u8,
+}
+
+impl<'a, B: I2cBus> McpPin<'a, B> {
+    /// Configure the pin as an output.
+    pub fn output(mcp: &'a RefCell<MCP230xx<B>>, pin: u8) -> Result<Self, CommunicationError> {
+        mcp.borrow_mut().setup(pin, Mode::Output)?;
+        Ok(Self { mcp, pin })
+    }
+
+    /// Configure the pin as an input, with or without its pull-up resistor.
+    pub fn input(
+        mcp: &'a RefCell<MCP230xx<B>>,
+        pin: u8,
+        pullup: bool,
+    ) -> Result<Self, CommunicationError> {
+        let mut device = mcp.borrow_mut();
+        device.setup(pin, Mode::Input)?;
+        device.pullup(
+            pin,
+            if pullup {
+                PullUpDown::PullUp
+            } else {
+                PullUpDown::Off
+            },
+        )?;
+        Ok(Self { mcp, pin })
+    }
+
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+}
+
+impl<B: I2cBus> ErrorType for McpPin<'_, B> {
+    type Error = CommunicationError;
+}
+
+impl<B: I2cBus> OutputPin for McpPin<'_, B> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.mcp.borrow_mut().output(self.pin, false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.mcp.borrow_mut().output(self.pin, true)
+    }
+}
+
+impl<B: I2cBus> StatefulOutputPin for McpPin<'_, B> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        let device = self.mcp.borrow();
+        Ok(device.gpio[(self.pin / 8) as usize] & 1 << (self.pin % 8) > 0)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_set_high()?)
+    }
+}
+
+impl<B: I2cBus> InputPin for McpPin<'_, B> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.mcp.borrow_mut().input(self.pin)? == Level::High)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
+
+#[test]
+fn pins() {
+    use crate::i2c::bus::mock::MockBus;
+
+    let bus = MockBus::new();
+    let mcp = RefCell::new(MCP230xx::mcp23017_with_bus(bus.clone()).unwrap());
+    let mut led = McpPin::output(&mcp, 6).unwrap();
+    let mut button = McpPin::input(&mcp, 0, true).unwrap();
+    assert!(McpPin::output(&mcp, 16).is_err());
+
+    led.set_high().unwrap();
+    assert!(led.is_set_high().unwrap());
+    assert_eq!(bus.registers(0x20)[0x12], 0x40);
+
+    bus.set_registers(0x20, 0x12, &[0x00]);
+    assert!(button.is_low().unwrap());
+}
@@ -0,0 +1,138 @@
+use crate::i2c::bus::I2cBus;
+use crate::i2c::lcd::{AdafruitDisplay, Frame};
+
+/// Pixel columns in a character cell.
+const CELL_WIDTH: u32 = 5;
+
+/// A horizontal bar, filled pixel column by pixel column: partially filled
+/// cells are drawn with custom characters, so a bar takes at most two
+/// CGRAM locations, or three when it has a threshold marker.
+///
+/// The bar is laid out on a single line from `col`, taking `width`
+/// characters along with its label and percentage, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bar {
+    col: u8,
+    line: u8,
+    width: u8,
+    label: Option<String>,
+    threshold: Option<f32>,
+    percentage: bool,
+}
+
+impl Bar {
+    pub fn new(col: u8, line: u8, width: u8) -> Self {
+        Bar {
+            col,
+            line,
+            width,
+            label: None,
+            threshold: None,
+            percentage: false,
+        }
+    }
+
+    /// Text shown before the bar, followed by a space.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Mark the given ratio, between 0 and 1, with a vertical line.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = Some(threshold.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Show the value as a percentage after the bar, like a progress bar.
+    pub fn percentage(mut self) -> Self {
+        self.percentage = true;
+        self
+    }
+
+    /// Number of characters left for the bar itself.
+    pub fn bar_width(&self) -> u8 {
+        let label = self
+            .label
+            .as_ref()
+            .map_or(0, |label| label.chars().count() + 1);
+        let percentage = if self.percentage { 5 } else { 0 };
+        (self.width as usize).saturating_sub(label + percentage) as u8
+    }
+
+    /// Draw the bar filled up to `value`, between 0 and 1, defining the
+    /// glyphs it needs on the display.
+    pub fn draw<B: I2cBus>(&self, display: &mut AdafruitDisplay<B>, frame: &mut Frame, value: f32) {
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        let mut text = String::new();
+        if let Some(ref label) = self.label {
+            text.push_str(label);
+            text.push(' ');
+        }
+
+        let cells = u32::from(self.bar_width());
+        let pixels = (value * (cells * CELL_WIDTH) as f32).round() as u32;
+        let marker = self.threshold.map(|t| {
+            ((t * (cells * CELL_WIDTH) as f32) as u32).min((cells * CELL_WIDTH).saturating_sub(1))
+        });
+        for cell in 0..cells {
+            let filled = pixels.saturating_sub(cell * CELL_WIDTH).min(CELL_WIDTH);
+            let marked = marker
+                .filter(|m| m / CELL_WIDTH == cell)
+                .map(|m| m % CELL_WIDTH);
+            text.push(match (filled, marked) {
+                (0, None) => ' ',
+                (CELL_WIDTH, None) => '█',
+                (filled, marked) => {
+                    let mut row = 0x1F_u8 << (CELL_WIDTH - filled) & 0x1F;
+                    if let Some(m) = marked {
+                        // The marker is a gap in the filled part.
+                        row ^= 0x10 >> m;
+                    }
+                    display.define_glyph(&format!("bar{:02x}", row), [row; 8])
+                }
+            });
+        }
+
+        if self.percentage {
+            text.push_str(&format!(" {:3.0}%", value * 100.0));
+        }
+        frame.write(self.col, self.line, &text);
+    }
+}
+
+#[test]
+fn draw() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let mut frame = display.frame();
+    // 7 cells of 5 pixels are left for the bar, 37.5% being 13 pixels.
+    Bar::new(0, 0, 16)
+        .label("Ads")
+        .percentage()
+        .draw(&mut display, &mut frame, 0.375);
+    // 2 cells, 30% being 3 pixels, with a marker at the 6th one.
+    Bar::new(4, 1, 2)
+        .threshold(0.5)
+        .draw(&mut display, &mut frame, 0.3);
+    display.render(&frame).unwrap();
+
+    let text: Vec<Vec<char>> = plate.text().iter().map(|l| l.chars().collect()).collect();
+    let line = |line: usize, range: std::ops::Range<usize>| -> String {
+        text[line][range].iter().collect()
+    };
+    assert_eq!(line(0, 0..6), "Ads \u{ff}\u{ff}");
+    assert_eq!(line(0, 7..16), "      38%");
+    let lcd = plate.lcd();
+    assert_eq!(lcd.glyph(text[0][6] as u8), [0x1C; 8]);
+    // Both partial cells are the same glyph.
+    assert_eq!(text[1][4], text[0][6]);
+    assert_eq!(lcd.glyph(text[1][5] as u8), [0x10; 8]);
+    assert!(plate.errors().is_empty());
+}
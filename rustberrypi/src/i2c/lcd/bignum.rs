@@ -0,0 +1,226 @@
+use crate::i2c::bus::I2cBus;
+use crate::i2c::lcd::{AdafruitDisplay, Frame};
+
+/// The segments digits are made of, in the order of `SEGMENT_NAMES`: left
+/// top corner, upper bar, right top corner, left bottom corner, lower bar,
+/// right bottom corner, upper and lower bars, and a dot.
+const SEGMENTS: [[u8; 8]; 8] = [
+    [0x07, 0x0F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F],
+    [0x1F, 0x1F, 0x1F, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x1C, 0x1E, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F],
+    [0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x0F, 0x07],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x1F, 0x1F, 0x1F],
+    [0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1E, 0x1C],
+    [0x1F, 0x1F, 0x1F, 0x00, 0x00, 0x00, 0x1F, 0x1F],
+    [0x00, 0x00, 0x00, 0x0E, 0x0E, 0x0E, 0x00, 0x00],
+];
+
+/// Segments are written `a` to `h` in layouts, `#` being a full block.
+const SEGMENT_NAMES: &str = "abcdefgh";
+
+/// Layout of a character on 2 rows.
+fn layout(c: char) -> Option<[&'static str; 2]> {
+    Some(match c {
+        '0' => ["abc", "def"],
+        '1' => ["bc ", "e#e"],
+        '2' => ["ggc", "dee"],
+        '3' => ["ggc", "eef"],
+        '4' => ["de#", "  #"],
+        '5' => ["dgg", "eef"],
+        '6' => ["agg", "def"],
+        '7' => ["bbc", "  #"],
+        '8' => ["agc", "def"],
+        '9' => ["agc", "eef"],
+        '.' | ',' => [" ", "e"],
+        ':' => ["h", "h"],
+        '%' => ["h/", "/h"],
+        '-' => ["ee", "  "],
+        ' ' => ["   ", "   "],
+        _ => return None,
+    })
+}
+
+/// Layout of a character on 4 rows. Digits are stretched from their 2 rows
+/// layout, each segment being split in an upper and a lower half.
+fn tall_layout(c: char) -> Option<[String; 4]> {
+    let split = |segment| match segment {
+        'a' => ('a', '#'),
+        'b' => ('b', ' '),
+        'c' => ('c', '#'),
+        'd' => ('#', 'd'),
+        'e' => (' ', 'e'),
+        'f' => ('#', 'f'),
+        'g' => ('b', 'e'),
+        other => (other, other),
+    };
+    let rows: [&str; 4] = match c {
+        '.' | ',' => [" ", " ", " ", "e"],
+        ':' => [" ", "h", "h", " "],
+        '%' => ["h ", " /", "/ ", " h"],
+        '-' => ["  ", "ee", "  ", "  "],
+        c => {
+            let [top, bottom] = layout(c)?;
+            return Some([
+                top.chars().map(|s| split(s).0).collect(),
+                top.chars().map(|s| split(s).1).collect(),
+                bottom.chars().map(|s| split(s).0).collect(),
+                bottom.chars().map(|s| split(s).1).collect(),
+            ]);
+        }
+    };
+    Some(rows.map(String::from))
+}
+
+/// Whether the character is drawn without a blank column around it.
+fn is_narrow(c: char) -> bool {
+    c == '.' || c == ',' || c == ':'
+}
+
+/// Draws digits two rows tall, or four on panels with enough lines, made
+/// of 8 custom characters: they take all the CGRAM locations, so no other
+/// custom character can be shown along with them.
+///
+/// Besides digits, the decimal point (or comma), percent sign, colon,
+/// minus sign and space are supported.
+pub struct BigFont {
+    segments: [char; 8],
+    rows: u8,
+}
+
+impl BigFont {
+    /// Define the segments on the display, using 4 rows if it has them.
+    pub fn new<B: I2cBus>(display: &mut AdafruitDisplay<B>) -> Self {
+        let rows = if display.frame().lines() >= 4 { 4 } else { 2 };
+        BigFont::with_rows(display, rows)
+    }
+
+    /// Define the segments on the display, for digits 2 or 4 rows tall.
+    pub fn with_rows<B: I2cBus>(display: &mut AdafruitDisplay<B>, rows: u8) -> Self {
+        let mut segments = [' '; 8];
+        for (i, pattern) in SEGMENTS.iter().enumerate() {
+            segments[i] = display.define_glyph(&format!("bignum{}", i), *pattern);
+        }
+        BigFont {
+            segments,
+            rows: if rows >= 4 { 4 } else { 2 },
+        }
+    }
+
+    pub fn rows(&self) -> u8 {
+        self.rows
+    }
+
+    fn rows_of(&self, c: char) -> Option<Vec<String>> {
+        if self.rows == 4 {
+            tall_layout(c).map(|rows| rows.to_vec())
+        } else {
+            layout(c).map(|rows| rows.iter().map(|row| row.to_string()).collect())
+        }
+    }
+
+    /// Number of columns taken by the text, unsupported characters being skipped.
+    pub fn width(&self, text: &str) -> u8 {
+        let mut frame = Frame::new(0, 0);
+        self.write(&mut frame, 0, 0, text)
+    }
+
+    /// Draw the text in the frame from the given position, which is the top
+    /// left corner of the first character. Characters are separated by a
+    /// blank column, except narrow ones like the decimal point. Returns the
+    /// number of columns taken.
+    pub fn write(&self, frame: &mut Frame, col: u8, line: u8, text: &str) -> u8 {
+        let mut x = col;
+        let mut previous: Option<char> = None;
+        for c in text.chars() {
+            let rows = match self.rows_of(c) {
+                Some(rows) => rows,
+                None => continue,
+            };
+            if previous.is_some_and(|p| !is_narrow(p)) && !is_narrow(c) {
+                for y in 0..self.rows {
+                    frame.set(x, line.saturating_add(y), ' ');
+                }
+                x = x.saturating_add(1);
+            }
+            for (y, row) in rows.iter().enumerate() {
+                for (dx, segment) in row.chars().enumerate() {
+                    let cell = match SEGMENT_NAMES.find(segment) {
+                        Some(i) => self.segments[i],
+                        None if segment == '#' => '█',
+                        None => segment,
+                    };
+                    frame.set(
+                        x.saturating_add(dx as u8),
+                        line.saturating_add(y as u8),
+                        cell,
+                    );
+                }
+            }
+            x = x.saturating_add(rows[0].chars().count() as u8);
+            previous = Some(c);
+        }
+        x - col
+    }
+}
+
+#[test]
+fn write() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let font = BigFont::new(&mut display);
+    assert_eq!(font.rows(), 2);
+    assert_eq!(font.width("12.5%"), 14);
+
+    let mut frame = display.frame();
+    assert_eq!(font.write(&mut frame, 1, 0, "12.5%"), 14);
+    display.render(&frame).unwrap();
+    // Name the segments after what was loaded in CGRAM.
+    let lcd = plate.lcd();
+    let segment = |code: u8| match SEGMENTS.iter().position(|p| *p == lcd.glyph(code)) {
+        Some(i) => SEGMENT_NAMES.as_bytes()[i] as char,
+        None => '?',
+    };
+    let text: Vec<String> = plate
+        .text()
+        .iter()
+        .map(|line| {
+            line.chars()
+                .map(|c| match c as u32 {
+                    0..=7 => segment(c as u8),
+                    0xFF => '#',
+                    _ => c,
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(text, [" bc  ggc dgg h/ ", " e#e deeeeef /h "]);
+    assert!(plate.errors().is_empty());
+
+    let tall = BigFont::with_rows(&mut display, 4);
+    let mut frame = Frame::new(20, 4);
+    assert_eq!(tall.write(&mut frame, 0, 0, "10:42"), 15);
+    let segment = |c: char| match SEGMENT_NAMES.find(c) {
+        Some(i) => tall.segments[i],
+        None if c == '#' => '█',
+        None => c,
+    };
+    let expected: Vec<String> = [
+        "bc  abc # # bbc",
+        " #  # #hde# ee#",
+        " #  # #h  # #  ",
+        "e#e def   # dee",
+    ]
+    .iter()
+    .map(|row| {
+        format!("{:20}", row)
+            .chars()
+            .map(segment)
+            .collect::<String>()
+    })
+    .collect();
+    for (line, row) in expected.iter().enumerate() {
+        assert_eq!(frame.line(line as u8).iter().collect::<String>(), *row);
+    }
+}
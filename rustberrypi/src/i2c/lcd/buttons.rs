@@ -0,0 +1,283 @@
+use rppal::gpio::{Gpio, Level, Mode, PullUpDown, Trigger};
+
+use std::time::{Duration, Instant};
+
+use crate::errors::CommunicationError;
+use crate::i2c::lcd::Button;
+
+/// Something that happened to one of the plate buttons.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ButtonEvent {
+    Pressed(Button),
+    Released(Button),
+    /// The button has been held down for the configured long-press duration.
+    LongPress(Button),
+    /// The button is still held down, sent periodically once the repeat delay elapsed.
+    Repeat(Button),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoRepeat {
+    /// How long a button has to be held before it starts repeating.
+    pub delay: Duration,
+    pub interval: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ButtonConfig {
+    /// Changes happening within this duration of the previous one are
+    /// considered bounces, and only taken into account once it elapsed.
+    pub debounce: Duration,
+    pub long_press: Option<Duration>,
+    pub repeat: Option<AutoRepeat>,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(30),
+            long_press: Some(Duration::from_secs(1)),
+            repeat: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ButtonState {
+    config: ButtonConfig,
+    /// Last level read, before debouncing.
+    raw: bool,
+    pressed: bool,
+    changed_at: Option<Instant>,
+    long_press_sent: bool,
+    next_repeat: Option<Instant>,
+}
+
+impl ButtonState {
+    fn settles_at(&self) -> Option<Instant> {
+        self.changed_at.map(|at| at + self.config.debounce)
+    }
+
+    fn long_press_at(&self) -> Option<Instant> {
+        match (self.pressed, self.long_press_sent, self.config.long_press) {
+            (true, false, Some(duration)) => self.changed_at.map(|at| at + duration),
+            _ => None,
+        }
+    }
+}
+
+/// Turns the levels of the buttons into debounced press, release,
+/// long-press and auto-repeat events.
+///
+/// It doesn't read the buttons itself: feed it with [`ButtonEvents::update`]
+/// whenever they were read, be it after an interrupt or periodically, and
+/// make sure to read them again by [`ButtonEvents::next_deadline`].
+pub struct ButtonEvents {
+    buttons: [ButtonState; 5],
+}
+
+impl ButtonEvents {
+    /// Track all the buttons with the same configuration.
+    pub fn new(config: ButtonConfig) -> Self {
+        let mut events = Self {
+            buttons: Default::default(),
+        };
+        for button in Button::ALL.iter() {
+            events.configure(*button, config);
+        }
+        events
+    }
+
+    pub fn configure(&mut self, button: Button, config: ButtonConfig) {
+        self.buttons[button as usize].config = config;
+    }
+
+    /// Process the levels of the buttons, indexed by `Button`, `true` meaning pressed.
+    pub fn update(&mut self, pressed: [bool; 5], now: Instant) -> Vec<ButtonEvent> {
+        let mut events = vec![];
+        for button in Button::ALL.iter() {
+            let state = &mut self.buttons[*button as usize];
+            state.raw = pressed[*button as usize];
+
+            let settled = state.settles_at().is_none_or(|at| now >= at);
+            if state.raw != state.pressed && settled {
+                state.pressed = state.raw;
+                state.changed_at = Some(now);
+                if state.pressed {
+                    state.long_press_sent = false;
+                    state.next_repeat = state.config.repeat.map(|repeat| now + repeat.delay);
+                    events.push(ButtonEvent::Pressed(*button));
+                } else {
+                    state.next_repeat = None;
+                    events.push(ButtonEvent::Released(*button));
+                }
+            }
+
+            if state.long_press_at().is_some_and(|at| now >= at) {
+                state.long_press_sent = true;
+                events.push(ButtonEvent::LongPress(*button));
+            }
+
+            if let (true, Some(at), Some(repeat)) =
+                (state.pressed, state.next_repeat, state.config.repeat)
+            {
+                if now >= at {
+                    // Don't try to catch up when the buttons weren't read in time.
+                    state.next_repeat = Some(if at + repeat.interval > now {
+                        at + repeat.interval
+                    } else {
+                        now + repeat.interval
+                    });
+                    events.push(ButtonEvent::Repeat(*button));
+                }
+            }
+        }
+        events
+    }
+
+    /// When the buttons have to be read again at the latest, even if nothing
+    /// changed: to settle a bouncing button or to send long-press and repeat
+    /// events. `None` if the buttons only need to be read on change.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.buttons
+            .iter()
+            .flat_map(|state| {
+                let settles_at = if state.raw != state.pressed {
+                    state.settles_at()
+                } else {
+                    None
+                };
+                let repeat_at = if state.pressed {
+                    state.next_repeat
+                } else {
+                    None
+                };
+                vec![settles_at, state.long_press_at(), repeat_at]
+            })
+            .flatten()
+            .min()
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.buttons[button as usize].pressed
+    }
+}
+
+/// A Raspberry Pi GPIO connected to the INTA or INTB line of the plate's
+/// MCP23017, which goes low when a button changed.
+pub struct InterruptLine {
+    gpio: Gpio,
+    pin: u8,
+}
+
+impl InterruptLine {
+    /// Watch the given BCM GPIO pin, using the Pi's pull-up as the
+    /// extender's line is configured as open-drain.
+    pub fn new(pin: u8) -> Result<Self, CommunicationError> {
+        let mut gpio = Gpio::new().map_err(CommunicationError::GpioError)?;
+        gpio.set_mode(pin, Mode::Input);
+        gpio.set_pullupdown(pin, PullUpDown::PullUp);
+        gpio.set_interrupt(pin, Trigger::FallingEdge)
+            .map_err(CommunicationError::GpioError)?;
+        Ok(Self { gpio, pin })
+    }
+
+    /// Whether an interrupt is currently raised.
+    pub fn is_active(&self) -> Result<bool, CommunicationError> {
+        Ok(self
+            .gpio
+            .read(self.pin)
+            .map_err(CommunicationError::GpioError)?
+            == Level::Low)
+    }
+
+    /// Block until the line goes low, or the timeout elapsed. Returns
+    /// whether an interrupt was raised.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<bool, CommunicationError> {
+        if self.is_active()? {
+            return Ok(true);
+        }
+        Ok(self
+            .gpio
+            .poll_interrupt(self.pin, false, timeout)
+            .map_err(CommunicationError::GpioError)?
+            .is_some())
+    }
+}
+
+#[cfg(test)]
+fn pressed(buttons: &[Button]) -> [bool; 5] {
+    let mut pressed = [false; 5];
+    for button in buttons {
+        pressed[*button as usize] = true;
+    }
+    pressed
+}
+
+#[test]
+fn debounce() {
+    let mut events = ButtonEvents::new(ButtonConfig::default());
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+
+    assert!(events.update(pressed(&[]), at(0)).is_empty());
+    assert_eq!(
+        events.update(pressed(&[Button::Up]), at(10)),
+        [ButtonEvent::Pressed(Button::Up)]
+    );
+    // Bounces are ignored, until things settle.
+    assert!(events.update(pressed(&[]), at(15)).is_empty());
+    assert!(events.update(pressed(&[Button::Up]), at(20)).is_empty());
+    assert!(events.update(pressed(&[]), at(25)).is_empty());
+    assert_eq!(events.next_deadline(), Some(at(40)));
+    assert_eq!(
+        events.update(pressed(&[]), at(40)),
+        [ButtonEvent::Released(Button::Up)]
+    );
+    assert!(!events.is_pressed(Button::Up));
+    assert_eq!(events.next_deadline(), None);
+}
+
+#[test]
+fn long_press_and_repeat() {
+    let mut events = ButtonEvents::new(ButtonConfig::default());
+    events.configure(
+        Button::Down,
+        ButtonConfig {
+            long_press: None,
+            repeat: Some(AutoRepeat {
+                delay: Duration::from_millis(500),
+                interval: Duration::from_millis(100),
+            }),
+            ..ButtonConfig::default()
+        },
+    );
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+
+    let both = pressed(&[Button::Select, Button::Down]);
+    assert_eq!(
+        events.update(both, at(0)),
+        [
+            ButtonEvent::Pressed(Button::Select),
+            ButtonEvent::Pressed(Button::Down)
+        ]
+    );
+    assert_eq!(events.next_deadline(), Some(at(500)));
+    assert_eq!(
+        events.update(both, at(500)),
+        [ButtonEvent::Repeat(Button::Down)]
+    );
+    assert_eq!(
+        events.update(both, at(600)),
+        [ButtonEvent::Repeat(Button::Down)]
+    );
+    assert_eq!(
+        events.update(both, at(1000)),
+        [
+            ButtonEvent::LongPress(Button::Select),
+            ButtonEvent::Repeat(Button::Down)
+        ]
+    );
+    assert_eq!(events.next_deadline(), Some(at(1100)));
+}
@@ -0,0 +1,159 @@
+use crate::i2c::lcd::charset;
+
+/// Number of custom characters the HD44780 can hold at once.
+pub const LOCATIONS: usize = 8;
+
+/// Named glyphs are drawn in frames with characters from the supplementary
+/// private use area, starting from this one.
+const NAMED_GLYPHS: u32 = 0xF_0000;
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    /// The character this location was loaded for: a named glyph, a
+    /// character missing from the ROM, or the location itself when it was
+    /// filled with `AdafruitDisplay::create_char`.
+    key: char,
+    pattern: [u8; 8],
+    last_used: u64,
+}
+
+/// Keeps track of what the CGRAM locations hold, and how many cells of the
+/// panel show each of them, to hand out locations to the glyphs of a frame.
+#[derive(Clone, Debug, Default)]
+pub struct GlyphCache {
+    names: Vec<(String, [u8; 8])>,
+    slots: [Option<Slot>; LOCATIONS],
+    refs: [usize; LOCATIONS],
+    clock: u64,
+}
+
+impl GlyphCache {
+    /// Register a glyph under the given name, or update its pattern, and
+    /// return the character standing for it in frames.
+    pub fn define(&mut self, name: &str, pattern: [u8; 8]) -> char {
+        let index = match self.names.iter().position(|(n, _)| n == name) {
+            Some(index) => {
+                self.names[index].1 = pattern;
+                index
+            }
+            None => {
+                self.names.push((name.to_string(), pattern));
+                self.names.len() - 1
+            }
+        };
+        std::char::from_u32(NAMED_GLYPHS + index as u32).unwrap()
+    }
+
+    /// Whether the character stands for a named glyph.
+    pub fn is_named(&self, c: char) -> bool {
+        (c as u32)
+            .checked_sub(NAMED_GLYPHS)
+            .is_some_and(|index| (index as usize) < self.names.len())
+    }
+
+    /// Pattern of a named glyph, or of a character missing from the ROM.
+    pub fn pattern(&self, c: char) -> Option<[u8; 8]> {
+        match (c as u32).checked_sub(NAMED_GLYPHS) {
+            Some(index) => self.names.get(index as usize).map(|(_, pattern)| *pattern),
+            None => charset::glyph(c),
+        }
+    }
+
+    /// Location currently holding the glyph for the given character.
+    pub fn location(&self, c: char) -> Option<u8> {
+        let pattern = self.pattern(c)?;
+        self.slots
+            .iter()
+            .position(|slot| slot.is_some_and(|s| s.key == c && s.pattern == pattern))
+            .map(|location| location as u8)
+    }
+
+    /// What the given location holds, if it was loaded.
+    pub fn get(&self, location: u8) -> Option<[u8; 8]> {
+        self.slots[location as usize % LOCATIONS].map(|slot| slot.pattern)
+    }
+
+    /// Record that the given location was loaded for a character.
+    pub fn load(&mut self, location: u8, c: char, pattern: [u8; 8]) {
+        self.clock += 1;
+        self.slots[location as usize % LOCATIONS] = Some(Slot {
+            key: c,
+            pattern,
+            last_used: self.clock,
+        });
+    }
+
+    /// Record that a cell showing the `old` character code now shows `new`.
+    pub fn replace(&mut self, old: u8, new: u8) {
+        if (old as usize) < LOCATIONS {
+            self.refs[old as usize] = self.refs[old as usize].saturating_sub(1);
+        }
+        if (new as usize) < LOCATIONS {
+            self.refs[new as usize] += 1;
+        }
+    }
+
+    /// Number of cells on the panel showing each location.
+    pub fn refs(&self) -> [usize; LOCATIONS] {
+        self.refs
+    }
+
+    /// Forget about the cells showing custom characters, e.g. once the
+    /// panel was cleared.
+    pub fn clear_refs(&mut self) {
+        self.refs = [0; LOCATIONS];
+    }
+
+    /// Hand out locations to the `required` characters, and to as many of
+    /// the `optional` ones as possible, without touching the `pinned`
+    /// locations. The least recently used glyphs are evicted first. Returns
+    /// the locations to load, or the number of locations that would be
+    /// needed when there aren't enough.
+    pub fn allocate(
+        &mut self,
+        required: &[char],
+        optional: &[char],
+        mut pinned: [bool; LOCATIONS],
+    ) -> Result<Vec<(u8, char, [u8; 8])>, usize> {
+        self.clock += 1;
+        for c in required.iter().chain(optional) {
+            if let Some(location) = self.location(*c) {
+                if let Some(ref mut slot) = self.slots[location as usize] {
+                    slot.last_used = self.clock;
+                }
+            }
+        }
+        let mut missing = vec![];
+        for c in required {
+            match self.location(*c) {
+                Some(location) => pinned[location as usize] = true,
+                None => missing.push(*c),
+            }
+        }
+        let needed = pinned.iter().filter(|pinned| **pinned).count() + missing.len();
+        if needed > LOCATIONS {
+            return Err(needed);
+        }
+
+        let mut loads = vec![];
+        for c in missing.into_iter().chain(optional.iter().cloned()) {
+            if let Some(location) = self.location(c) {
+                pinned[location as usize] = true;
+                continue;
+            }
+            // Free locations first, then the least recently used one.
+            let victim = (0..LOCATIONS)
+                .filter(|location| !pinned[*location])
+                .min_by_key(|location| self.slots[*location].map_or(0, |s| s.last_used + 1));
+            let location = match victim {
+                Some(location) => location,
+                None => break,
+            };
+            pinned[location] = true;
+            let pattern = self.pattern(c).unwrap();
+            self.load(location as u8, c, pattern);
+            loads.push((location as u8, c, pattern));
+        }
+        Ok(loads)
+    }
+}
@@ -0,0 +1,207 @@
+/// The character generator ROM the HD44780 was manufactured with, which
+/// defines what the codes from 0x10 to 0xFF look like.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharacterRom {
+    /// Japanese standard font: ASCII (with `¥` instead of the backslash),
+    /// half-width katakana and some Greek and math symbols.
+    A00,
+    /// European standard font: ASCII, arrows and most of ISO-8859-1.
+    A02,
+}
+
+/// Characters of the A00 ROM outside of ASCII and half-width katakana.
+const A00: &[(u8, char)] = &[
+    (0x5C, '¥'),
+    (0x7E, '→'),
+    (0x7F, '←'),
+    (0xDF, '°'),
+    (0xE0, 'α'),
+    (0xE1, 'ä'),
+    (0xE2, 'β'),
+    (0xE3, 'ε'),
+    (0xE4, 'µ'),
+    (0xE4, 'μ'),
+    (0xE5, 'σ'),
+    (0xE6, 'ρ'),
+    (0xE8, '√'),
+    (0xEC, '¢'),
+    (0xED, '£'),
+    (0xEE, 'ñ'),
+    (0xEF, 'ö'),
+    (0xF2, 'θ'),
+    (0xF3, '∞'),
+    (0xF4, 'Ω'),
+    (0xF5, 'ü'),
+    (0xF6, 'Σ'),
+    (0xF7, 'π'),
+    (0xFA, '千'),
+    (0xFB, '万'),
+    (0xFC, '円'),
+    (0xFD, '÷'),
+    (0xFF, '█'),
+];
+
+/// Characters of the A02 ROM outside of ASCII and the ISO-8859-1 ranges.
+const A02: &[(u8, char)] = &[
+    (0x10, '▶'),
+    (0x11, '◀'),
+    (0x18, '↑'),
+    (0x19, '↓'),
+    (0x1A, '→'),
+    (0x1B, '←'),
+    (0x1C, '≤'),
+    (0x1D, '≥'),
+    (0x1E, '▲'),
+    (0x1F, '▼'),
+];
+
+/// Codes of the A02 ROM between 0xA0 and 0xBF that match ISO-8859-1.
+const A02_LATIN1: &[u8] = &[
+    0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA9, 0xAA, 0xAB, 0xAE, 0xB0, 0xB1, 0xB2, 0xB3, 0xB5,
+    0xB6, 0xB7, 0xB9, 0xBA, 0xBB, 0xBC, 0xBD, 0xBE, 0xBF,
+];
+
+impl CharacterRom {
+    /// Code of the given character in this ROM, if it has it. Custom
+    /// characters (`'\u{0}'` to `'\u{7}'`) are never part of the ROM.
+    pub fn encode(self, c: char) -> Option<u8> {
+        let code = c as u32;
+        match self {
+            CharacterRom::A00 => {
+                if (0x20..0x7E).contains(&code) && code != 0x5C {
+                    return Some(code as u8);
+                }
+                // Half-width katakana are laid out in the same order as in Unicode.
+                if (0xFF61..=0xFF9F).contains(&code) {
+                    return Some((code - 0xFF61 + 0xA1) as u8);
+                }
+                A00.iter().find(|(_, ch)| *ch == c).map(|(code, _)| *code)
+            }
+            CharacterRom::A02 => {
+                let latin1 = (0xC0..=0xFF).contains(&code)
+                    || A02_LATIN1.iter().any(|latin1| u32::from(*latin1) == code);
+                if (0x20..0x7F).contains(&code) || latin1 {
+                    return Some(code as u8);
+                }
+                A02.iter().find(|(_, ch)| *ch == c).map(|(code, _)| *code)
+            }
+        }
+    }
+
+    /// Character shown for the given code, if known. Codes below 0x10 are
+    /// custom characters, and are returned as is.
+    pub fn decode(self, code: u8) -> Option<char> {
+        if code < 0x10 {
+            return Some(char::from(code));
+        }
+        let table = match self {
+            CharacterRom::A00 => A00,
+            CharacterRom::A02 => A02,
+        };
+        if let Some((_, c)) = table.iter().find(|(c, _)| *c == code) {
+            return Some(*c);
+        }
+        match self {
+            CharacterRom::A00 if (0xA1..=0xDF).contains(&code) => {
+                std::char::from_u32(u32::from(code) - 0xA1 + 0xFF61)
+            }
+            CharacterRom::A00 if (0x20..0x7E).contains(&code) => Some(char::from(code)),
+            CharacterRom::A02 if self.encode(char::from(code)) == Some(code) => {
+                Some(char::from(code))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A 5x8 pattern for characters missing from some ROMs, to be loaded in a
+/// free CGRAM location.
+pub fn glyph(c: char) -> Option<[u8; 8]> {
+    Some(match c {
+        '°' => [0x0C, 0x12, 0x12, 0x0C, 0x00, 0x00, 0x00, 0x00],
+        '€' => [0x07, 0x08, 0x1E, 0x08, 0x1E, 0x08, 0x07, 0x00],
+        '\\' => [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00, 0x00],
+        '~' => [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00, 0x00],
+        '↑' => [0x04, 0x0E, 0x15, 0x04, 0x04, 0x04, 0x04, 0x00],
+        '↓' => [0x04, 0x04, 0x04, 0x04, 0x15, 0x0E, 0x04, 0x00],
+        '→' => [0x00, 0x04, 0x02, 0x1F, 0x02, 0x04, 0x00, 0x00],
+        '←' => [0x00, 0x04, 0x08, 0x1F, 0x08, 0x04, 0x00, 0x00],
+        'é' => [0x02, 0x04, 0x0E, 0x11, 0x1F, 0x10, 0x0E, 0x00],
+        'è' => [0x08, 0x04, 0x0E, 0x11, 0x1F, 0x10, 0x0E, 0x00],
+        'ê' => [0x04, 0x0A, 0x0E, 0x11, 0x1F, 0x10, 0x0E, 0x00],
+        'à' => [0x08, 0x04, 0x0E, 0x01, 0x0F, 0x11, 0x0F, 0x00],
+        'ç' => [0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E, 0x04, 0x0C],
+        'Ä' => [0x0A, 0x00, 0x0E, 0x11, 0x1F, 0x11, 0x11, 0x00],
+        'Ö' => [0x0A, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E, 0x00],
+        'Ü' => [0x0A, 0x00, 0x11, 0x11, 0x11, 0x11, 0x0E, 0x00],
+        'ß' => [0x0C, 0x12, 0x12, 0x16, 0x11, 0x11, 0x16, 0x00],
+        '…' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x15, 0x00],
+        '█' => [0x1F; 8],
+        '✓' => [0x00, 0x01, 0x03, 0x16, 0x1C, 0x08, 0x00, 0x00],
+        '✗' => [0x00, 0x11, 0x1B, 0x0E, 0x0E, 0x1B, 0x11, 0x00],
+        _ => return None,
+    })
+}
+
+/// A look-alike for characters that are neither in the ROM nor have a
+/// glyph, or when the CGRAM is full. Always a single character, so text
+/// keeps its width.
+pub fn transliterate(c: char) -> Option<char> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'A',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'È' | 'É' | 'Ê' | 'Ë' | '€' => 'E',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | '°' => 'o',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => 'O',
+        'ù' | 'ú' | 'û' | 'ü' | 'µ' | 'μ' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' => 'U',
+        'ç' => 'c',
+        'Ç' => 'C',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ß' => 's',
+        'ý' | 'ÿ' => 'y',
+        '×' | '✗' => 'x',
+        '✓' => '+',
+        '→' | '»' | '▶' => '>',
+        '←' | '«' | '◀' => '<',
+        '↑' | '▲' => '^',
+        '↓' | '▼' => 'v',
+        '‘' | '’' | '´' => '\'',
+        '“' | '”' | '„' => '"',
+        '–' | '—' | '−' => '-',
+        '…' | '·' | '•' => '.',
+        '\\' => '/',
+        '█' => '#',
+        '¥' => 'Y',
+        '£' => 'L',
+        '\u{a0}' => ' ',
+        _ => return None,
+    })
+}
+
+#[test]
+fn encode() {
+    assert_eq!(CharacterRom::A00.encode('A'), Some(b'A'));
+    assert_eq!(CharacterRom::A00.encode('\\'), None);
+    assert_eq!(CharacterRom::A00.encode('°'), Some(0xDF));
+    assert_eq!(CharacterRom::A00.encode('ｱ'), Some(0xB1));
+    assert_eq!(CharacterRom::A00.encode('é'), None);
+    assert_eq!(CharacterRom::A02.encode('é'), Some(0xE9));
+    assert_eq!(CharacterRom::A02.encode('°'), Some(0xB0));
+    assert_eq!(CharacterRom::A02.encode('→'), Some(0x1A));
+    assert_eq!(CharacterRom::A02.encode('€'), None);
+}
+
+#[test]
+fn decode() {
+    assert_eq!(CharacterRom::A00.decode(0xDF), Some('°'));
+    assert_eq!(CharacterRom::A00.decode(0xB1), Some('ｱ'));
+    assert_eq!(CharacterRom::A00.decode(0x5C), Some('¥'));
+    assert_eq!(CharacterRom::A02.decode(0xE9), Some('é'));
+    assert_eq!(CharacterRom::A02.decode(0x03), Some('\u{3}'));
+    assert_eq!(CharacterRom::A02.decode(0x80), None);
+}
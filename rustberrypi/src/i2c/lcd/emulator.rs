@@ -0,0 +1,741 @@
+use rppal::i2c;
+
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::errors::CommunicationError;
+use crate::i2c::bus::I2cBus;
+use crate::i2c::lcd::{
+    Button, LCD_PLATE_BLUE, LCD_PLATE_D4, LCD_PLATE_D5, LCD_PLATE_D6, LCD_PLATE_D7, LCD_PLATE_EN,
+    LCD_PLATE_GREEN, LCD_PLATE_RED, LCD_PLATE_RS,
+};
+
+// MCP23017 registers, in ICON.BANK = 0 mode.
+const IODIR: usize = 0x00;
+const GPINTEN: usize = 0x04;
+const DEFVAL: usize = 0x06;
+const INTCON: usize = 0x08;
+const GPPU: usize = 0x0C;
+const INTF: usize = 0x0E;
+const INTCAP: usize = 0x10;
+const GPIO: usize = 0x12;
+const OLAT: usize = 0x14;
+
+const MCP23017_ADDRESS: u16 = 0x20;
+
+/// Clear display and return home take 1.52ms to execute, during which the
+/// controller ignores any other instruction.
+const SLOW_COMMAND_DURATION: Duration = Duration::from_micros(1520);
+
+/// A mistake in the way the HD44780 is being driven.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    /// An instruction was sent while the controller was still busy
+    /// executing a clear or return home command.
+    Busy { instruction: u8, rs: bool },
+    /// The DDRAM address is outside of the configured display geometry.
+    AddressOutOfRange(u8),
+    /// The RS line changed between the two nibbles of a 4-bit transfer.
+    NibbleMismatch,
+    /// Data was written before the controller was put in 4-bit mode.
+    NotInitialized(u8),
+    /// The I2C bus addressed a slave other than the MCP23017.
+    UnknownSlave(u16),
+}
+
+/// Where the address counter currently points to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressTarget {
+    Ddram,
+    Cgram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntryMode {
+    pub increment: bool,
+    pub shift: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayControl {
+    pub display: bool,
+    pub cursor: bool,
+    pub blink: bool,
+}
+
+/// Software model of a HD44780 character LCD controller.
+#[derive(Debug, Clone)]
+pub struct Hd44780 {
+    cols: u8,
+    lines: u8,
+    four_bits: bool,
+    two_lines: bool,
+    large_font: bool,
+    ddram: [u8; 0x80],
+    cgram: [u8; 0x40],
+    address: u8,
+    target: AddressTarget,
+    entry_mode: EntryMode,
+    display_control: DisplayControl,
+    shift: u8,
+    pending_nibble: Option<(u8, bool)>,
+    busy_until: Option<Instant>,
+    instructions: usize,
+    errors: Vec<ProtocolError>,
+}
+
+impl Hd44780 {
+    /// A controller wired to a panel of `cols` by `lines` characters, in its power-on state.
+    pub fn new(cols: u8, lines: u8) -> Self {
+        Self {
+            cols,
+            lines,
+            four_bits: false,
+            two_lines: false,
+            large_font: false,
+            ddram: [0x20; 0x80],
+            cgram: [0; 0x40],
+            address: 0,
+            target: AddressTarget::Ddram,
+            entry_mode: EntryMode {
+                increment: true,
+                shift: false,
+            },
+            display_control: DisplayControl {
+                display: false,
+                cursor: false,
+                blink: false,
+            },
+            shift: 0,
+            pending_nibble: None,
+            busy_until: None,
+            instructions: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Latch the data lines, as happens on the falling edge of the enable line.
+    pub fn latch(&mut self, nibble: u8, rs: bool) {
+        let nibble = nibble & 0x0F;
+        if !self.four_bits {
+            // In 8-bit mode, the lower data lines aren't connected and read as 0.
+            self.execute(nibble << 4, rs);
+            return;
+        }
+        match self.pending_nibble.take() {
+            None => self.pending_nibble = Some((nibble, rs)),
+            Some((high, high_rs)) => {
+                if high_rs != rs {
+                    self.errors.push(ProtocolError::NibbleMismatch);
+                }
+                self.execute(high << 4 | nibble, rs);
+            }
+        }
+    }
+
+    fn execute(&mut self, value: u8, rs: bool) {
+        self.instructions += 1;
+        let now = Instant::now();
+        if let Some(busy_until) = self.busy_until.take() {
+            if now < busy_until {
+                self.errors.push(ProtocolError::Busy {
+                    instruction: value,
+                    rs,
+                });
+            }
+        }
+
+        if rs {
+            self.write_data(value);
+            return;
+        }
+
+        if value & 0x80 != 0 {
+            let address = value & 0x7F;
+            if !self.is_visible_address(address) {
+                self.errors.push(ProtocolError::AddressOutOfRange(address));
+            }
+            self.address = address;
+            self.target = AddressTarget::Ddram;
+        } else if value & 0x40 != 0 {
+            self.address = value & 0x3F;
+            self.target = AddressTarget::Cgram;
+        } else if value & 0x20 != 0 {
+            if value & 0x10 == 0 && !self.four_bits {
+                // This nibble was the whole instruction, next ones are paired.
+                self.four_bits = true;
+                self.pending_nibble = None;
+            }
+            self.two_lines = value & 0x08 != 0;
+            self.large_font = value & 0x04 != 0;
+        } else if value & 0x10 != 0 {
+            let right = value & 0x04 != 0;
+            if value & 0x08 != 0 {
+                self.shift_display(right);
+            } else {
+                self.move_address(right);
+            }
+        } else if value & 0x08 != 0 {
+            self.display_control = DisplayControl {
+                display: value & 0x04 != 0,
+                cursor: value & 0x02 != 0,
+                blink: value & 0x01 != 0,
+            };
+        } else if value & 0x04 != 0 {
+            self.entry_mode = EntryMode {
+                increment: value & 0x02 != 0,
+                shift: value & 0x01 != 0,
+            };
+        } else if value & 0x02 != 0 {
+            self.address = 0;
+            self.target = AddressTarget::Ddram;
+            self.shift = 0;
+            self.busy_until = Some(now + SLOW_COMMAND_DURATION);
+        } else if value & 0x01 != 0 {
+            self.ddram = [0x20; 0x80];
+            self.address = 0;
+            self.target = AddressTarget::Ddram;
+            self.shift = 0;
+            self.entry_mode.increment = true;
+            self.busy_until = Some(now + SLOW_COMMAND_DURATION);
+        }
+    }
+
+    fn write_data(&mut self, value: u8) {
+        if !self.four_bits {
+            self.errors.push(ProtocolError::NotInitialized(value));
+        }
+        match self.target {
+            AddressTarget::Cgram => {
+                self.cgram[self.address as usize] = value & 0x1F;
+                self.address = if self.entry_mode.increment {
+                    (self.address + 1) & 0x3F
+                } else {
+                    self.address.wrapping_sub(1) & 0x3F
+                };
+            }
+            AddressTarget::Ddram => {
+                self.ddram[self.address as usize] = value;
+                let increment = self.entry_mode.increment;
+                self.move_address(increment);
+                if self.entry_mode.shift {
+                    // The display follows the cursor, in the opposite direction.
+                    self.shift_display(!increment);
+                }
+            }
+        }
+    }
+
+    /// Length of a DDRAM line, which depends on the number of lines
+    /// the controller is configured for.
+    fn line_length(&self) -> u8 {
+        if self.two_lines {
+            40
+        } else {
+            80
+        }
+    }
+
+    fn move_address(&mut self, right: bool) {
+        if self.target == AddressTarget::Cgram {
+            self.address = if right {
+                (self.address + 1) & 0x3F
+            } else {
+                self.address.wrapping_sub(1) & 0x3F
+            };
+            return;
+        }
+        // Both modes have 80 characters of DDRAM, split in two lines in 2-line mode.
+        let position = if self.two_lines {
+            (self.address / 0x40) * 40 + self.address % 0x40
+        } else {
+            self.address
+        };
+        let position = if right {
+            (position + 1) % 80
+        } else {
+            (position + 79) % 80
+        };
+        self.address = if self.two_lines {
+            (position / 40) * 0x40 + position % 40
+        } else {
+            position
+        };
+    }
+
+    fn shift_display(&mut self, right: bool) {
+        let length = self.line_length();
+        self.shift = if right {
+            (self.shift + length - 1) % length
+        } else {
+            (self.shift + 1) % length
+        };
+    }
+
+    /// DDRAM address of the first character of a row, while the display isn't shifted.
+    fn row_offset(&self, row: u8) -> u8 {
+        match row {
+            0 => 0x00,
+            1 => 0x40,
+            2 => self.cols,
+            _ => 0x40 + self.cols,
+        }
+    }
+
+    /// Whether a DDRAM address can be shown by a panel of the configured
+    /// geometry, possibly after shifting the display.
+    fn is_visible_address(&self, address: u8) -> bool {
+        if self.lines == 1 && !self.two_lines {
+            return address < 80;
+        }
+        let width = if self.lines > 2 { self.cols } else { 40 };
+        (0..self.lines).any(|row| {
+            let offset = self.row_offset(row);
+            address >= offset && address < offset + width
+        })
+    }
+
+    /// DDRAM address shown at the given position of the panel.
+    fn visible_address(&self, col: u8, row: u8) -> u8 {
+        let length = self.line_length();
+        let offset = self.row_offset(row);
+        let (line, start) = (offset / 0x40, offset % 0x40);
+        let position = (start + col + self.shift) % length;
+        line * 0x40 + position
+    }
+
+    /// The characters currently displayed on the given row, CGRAM glyphs
+    /// showing up as `'\u{0}'` to `'\u{7}'`.
+    pub fn line(&self, row: u8) -> String {
+        (0..self.cols)
+            .map(|col| self.ddram[self.visible_address(col, row) as usize] as char)
+            .collect()
+    }
+
+    /// The characters currently displayed, one string per row.
+    pub fn text(&self) -> Vec<String> {
+        (0..self.lines).map(|row| self.line(row)).collect()
+    }
+
+    pub fn ddram(&self) -> &[u8] {
+        &self.ddram
+    }
+
+    pub fn cgram(&self) -> &[u8] {
+        &self.cgram
+    }
+
+    /// The 8 rows of pixels of one of the custom characters.
+    pub fn glyph(&self, location: u8) -> [u8; 8] {
+        let mut glyph = [0; 8];
+        let start = (location & 0x7) as usize * 8;
+        glyph.copy_from_slice(&self.cgram[start..start + 8]);
+        glyph
+    }
+
+    pub fn address(&self) -> (AddressTarget, u8) {
+        (self.target, self.address)
+    }
+
+    /// Position of the cursor on the panel, if it is on a visible cell.
+    pub fn cursor(&self) -> Option<(u8, u8)> {
+        if self.target != AddressTarget::Ddram {
+            return None;
+        }
+        (0..self.lines)
+            .flat_map(|row| (0..self.cols).map(move |col| (col, row)))
+            .find(|(col, row)| self.visible_address(*col, *row) == self.address)
+    }
+
+    pub fn entry_mode(&self) -> EntryMode {
+        self.entry_mode
+    }
+
+    pub fn display_control(&self) -> DisplayControl {
+        self.display_control
+    }
+
+    /// How many characters the display was shifted to the left.
+    pub fn display_shift(&self) -> u8 {
+        self.shift
+    }
+
+    pub fn is_four_bits(&self) -> bool {
+        self.four_bits
+    }
+
+    pub fn is_two_lines(&self) -> bool {
+        self.two_lines
+    }
+
+    /// How many instructions and data writes were executed so far.
+    pub fn instruction_count(&self) -> usize {
+        self.instructions
+    }
+
+    pub fn errors(&self) -> &[ProtocolError] {
+        &self.errors
+    }
+}
+
+struct State {
+    address: u16,
+    registers: [u8; 0x16],
+    pressed: u16,
+    lcd: Hd44780,
+}
+
+impl State {
+    fn pins(&self, register: usize) -> u16 {
+        u16::from(self.registers[register]) | u16::from(self.registers[register + 1]) << 8
+    }
+
+    /// Level of the lines going out of the extender.
+    fn outputs(&self) -> u16 {
+        self.pins(OLAT) & !self.pins(IODIR)
+    }
+
+    /// Level of all the pins as read through the GPIO register. Buttons
+    /// pull their pin to ground when pressed.
+    fn levels(&self) -> u16 {
+        let inputs = self.pins(IODIR);
+        let pulled_up = self.pins(GPPU) & !self.pressed;
+        (self.outputs() & !inputs) | (pulled_up & inputs)
+    }
+
+    fn set_pins(&mut self, register: usize, pins: u16) {
+        self.registers[register] = pins as u8;
+        self.registers[register + 1] = (pins >> 8) as u8;
+    }
+
+    /// Flag the pins whose interrupt condition is met, `previous` being the
+    /// level of the pins before the last change.
+    fn update_interrupts(&mut self, previous: u16) {
+        let levels = self.levels();
+        let compare = self.pins(INTCON);
+        let triggered = self.pins(GPINTEN)
+            & self.pins(IODIR)
+            & ((!compare & (levels ^ previous)) | (compare & (levels ^ self.pins(DEFVAL))));
+        if triggered == 0 {
+            return;
+        }
+        // Levels are only captured when the interrupt is first raised.
+        if self.pins(INTF) == 0 {
+            self.set_pins(INTCAP, levels);
+        }
+        let flags = self.pins(INTF) | triggered;
+        self.set_pins(INTF, flags);
+    }
+
+    fn write(&mut self, command: u8, buffer: &[u8]) {
+        let before = self.outputs();
+        for (i, value) in buffer.iter().enumerate() {
+            let mut register = (command as usize + i) % self.registers.len();
+            match register {
+                // Interrupt flags and captured levels are read-only.
+                INTF..=0x11 => continue,
+                // Writing to the port modifies the output latch.
+                GPIO | 0x13 => register += OLAT - GPIO,
+                _ => {}
+            }
+            self.registers[register] = *value;
+        }
+        let after = self.outputs();
+
+        let bit = |pins: u16, pin: u8| pins & (1 << pin) != 0;
+        if bit(before, LCD_PLATE_EN) && !bit(after, LCD_PLATE_EN) {
+            let nibble = [LCD_PLATE_D4, LCD_PLATE_D5, LCD_PLATE_D6, LCD_PLATE_D7]
+                .iter()
+                .enumerate()
+                .fold(0, |nibble, (i, pin)| nibble | (bit(after, *pin) as u8) << i);
+            self.lcd.latch(nibble, bit(after, LCD_PLATE_RS));
+        }
+    }
+
+    fn read(&mut self, command: u8, buffer: &mut [u8]) {
+        let levels = self.levels();
+        let mut clear_interrupt = false;
+        for (i, value) in buffer.iter_mut().enumerate() {
+            let register = (command as usize + i) % self.registers.len();
+            *value = match register {
+                GPIO => levels as u8,
+                r if r == GPIO + 1 => (levels >> 8) as u8,
+                r => self.registers[r],
+            };
+            // Reading the port or the captured levels clears the interrupt.
+            clear_interrupt |= (INTCAP..=0x13).contains(&register);
+        }
+        if clear_interrupt {
+            self.set_pins(INTF, 0);
+            // Pins compared to their default value raise it again right away.
+            self.update_interrupts(levels);
+        }
+    }
+}
+
+/// Emulates the Adafruit RGB LCD plate: a MCP23017 GPIO extender driving a
+/// HD44780 controller, the RGB backlight and the five buttons.
+///
+/// The HD44780 is rebuilt from the pin transitions the driver makes through
+/// the extender, so this can be used in place of the real I2C bus to check
+/// what `AdafruitDisplay` would have shown.
+///
+/// The emulator is a cheap handle on shared state, it can be cloned to keep
+/// an eye on the panel after handing it over to the driver.
+#[derive(Clone)]
+pub struct VirtualBackplate {
+    state: Arc<Mutex<State>>,
+}
+
+impl VirtualBackplate {
+    /// A plate with a panel of `cols` by `lines` characters.
+    pub fn new(cols: u8, lines: u8) -> Self {
+        let mut registers = [0; 0x16];
+        // All pins are inputs on power-on.
+        registers[IODIR] = 0xFF;
+        registers[IODIR + 1] = 0xFF;
+        Self {
+            state: Arc::new(Mutex::new(State {
+                address: MCP23017_ADDRESS,
+                registers,
+                pressed: 0,
+                lcd: Hd44780::new(cols, lines),
+            })),
+        }
+    }
+
+    /// The "Adafruit i2c 16x2 RGB LCD Pi Plate".
+    pub fn for_backplate() -> Self {
+        Self::new(16, 2)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state stays consistent even if a holder of the lock panicked.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A snapshot of the HD44780 controller.
+    pub fn lcd(&self) -> Hd44780 {
+        self.state().lcd.clone()
+    }
+
+    /// The characters currently displayed, one string per row.
+    pub fn text(&self) -> Vec<String> {
+        self.state().lcd.text()
+    }
+
+    /// Protocol mistakes made so far.
+    pub fn errors(&self) -> Vec<ProtocolError> {
+        self.state().lcd.errors.clone()
+    }
+
+    /// Whether the red, green and blue backlight LEDs are lit, they
+    /// are turned on by pulling their line low.
+    pub fn backlight(&self) -> (bool, bool, bool) {
+        let state = self.state();
+        let lit =
+            |pin: u8| state.pins(IODIR) & (1 << pin) == 0 && state.outputs() & (1 << pin) == 0;
+        (
+            lit(LCD_PLATE_RED),
+            lit(LCD_PLATE_GREEN),
+            lit(LCD_PLATE_BLUE),
+        )
+    }
+
+    pub fn press(&self, button: Button) {
+        let mut state = self.state();
+        let previous = state.levels();
+        state.pressed |= 1 << button as u8;
+        state.update_interrupts(previous);
+    }
+
+    pub fn release(&self, button: Button) {
+        let mut state = self.state();
+        let previous = state.levels();
+        state.pressed &= !(1 << button as u8);
+        state.update_interrupts(previous);
+    }
+
+    /// Whether the extender is raising an interrupt, assuming the INTA
+    /// and INTB lines are mirrored.
+    pub fn interrupt(&self) -> bool {
+        self.state().pins(INTF) != 0
+    }
+
+    fn check_address(&self, state: &mut State) -> Result<(), CommunicationError> {
+        if state.address == MCP23017_ADDRESS {
+            return Ok(());
+        }
+        let error = ProtocolError::UnknownSlave(state.address);
+        state.lcd.errors.push(error);
+        Err(CommunicationError::BusError(i2c::Error::Io(
+            io::Error::other("no device acknowledged the address"),
+        )))
+    }
+}
+
+impl I2cBus for VirtualBackplate {
+    fn set_slave_address(&mut self, address: u16) -> Result<(), CommunicationError> {
+        self.state().address = address;
+        Ok(())
+    }
+
+    fn block_write(&self, command: u8, buffer: &[u8]) -> Result<(), CommunicationError> {
+        let mut state = self.state();
+        self.check_address(&mut state)?;
+        state.write(command, buffer);
+        Ok(())
+    }
+
+    fn block_read(&self, command: u8, buffer: &mut [u8]) -> Result<(), CommunicationError> {
+        let mut state = self.state();
+        self.check_address(&mut state)?;
+        state.read(command, buffer);
+        Ok(())
+    }
+
+    fn smbus_send_byte(&self, _value: u8) -> Result<(), CommunicationError> {
+        let mut state = self.state();
+        self.check_address(&mut state)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, CommunicationError> {
+        if let Some((command, data)) = buffer.split_first() {
+            self.block_write(*command, data)?;
+        }
+        Ok(buffer.len())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, CommunicationError> {
+        self.block_read(GPIO as u8, buffer)?;
+        Ok(buffer.len())
+    }
+}
+
+/// A display driven through the emulated plate, for the tests.
+#[cfg(test)]
+pub(crate) fn display(plate: &VirtualBackplate) -> crate::i2c::lcd::AdafruitDisplay<VirtualBackplate> {
+    use crate::i2c::io::MCP230xx;
+    use crate::i2c::lcd::AdafruitDisplay;
+
+    AdafruitDisplay::for_backplate_with(MCP230xx::mcp23017_with_bus(plate.clone()).unwrap())
+        .unwrap()
+}
+
+#[test]
+fn message() {
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = display(&plate);
+    assert!(plate.lcd().is_four_bits());
+    assert!(plate.lcd().is_two_lines());
+    assert_eq!(plate.backlight(), (true, true, true));
+
+    display.message("Blocked 123 ads\n42.0% less junk").unwrap();
+    assert_eq!(plate.text(), ["Blocked 123 ads ", "42.0% less junk "]);
+    assert_eq!(plate.lcd().cursor(), Some((15, 1)));
+    assert!(plate.errors().is_empty());
+}
+
+#[test]
+fn custom_characters() {
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = display(&plate);
+    let c = display
+        .create_char(2, [0x1F, 0, 0x1F, 0, 0x1F, 0, 0x1F, 0])
+        .unwrap();
+    display.set_cursor(3, 0).unwrap();
+    display.message(&c.to_string()).unwrap();
+
+    let lcd = plate.lcd();
+    assert_eq!(lcd.glyph(2), [0x1F, 0, 0x1F, 0, 0x1F, 0, 0x1F, 0]);
+    assert_eq!(lcd.line(0), "   \u{2}            ");
+}
+
+#[test]
+fn display_control() {
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = display(&plate);
+    display.blink(true).unwrap();
+    display.autoscroll(true).unwrap();
+
+    let lcd = plate.lcd();
+    assert!(lcd.display_control().display);
+    assert!(lcd.display_control().blink);
+    assert!(!lcd.display_control().cursor);
+    assert!(lcd.entry_mode().increment);
+    assert!(lcd.entry_mode().shift);
+}
+
+#[test]
+fn protocol_errors() {
+    let plate = VirtualBackplate::for_backplate();
+    let _display = display(&plate);
+    let mut bus = plate.clone();
+
+    // Bit-bang the instructions by hand, without the delays.
+    let send = |bus: &mut VirtualBackplate, value: u8, rs: bool| {
+        for nibble in &[value >> 4, value & 0x0F] {
+            let mut pins = (rs as u16) << LCD_PLATE_RS;
+            for (i, pin) in [LCD_PLATE_D4, LCD_PLATE_D5, LCD_PLATE_D6, LCD_PLATE_D7]
+                .iter()
+                .enumerate()
+            {
+                pins |= u16::from(nibble >> i & 1) << pin;
+            }
+            for en in &[0, 1 << LCD_PLATE_EN, 0] {
+                let pins = pins | en;
+                bus.block_write(GPIO as u8, &[pins as u8, (pins >> 8) as u8])
+                    .unwrap();
+            }
+        }
+    };
+    send(&mut bus, 0x01, false);
+    send(&mut bus, b'A', true);
+    send(&mut bus, 0x80 | 0x30, false);
+
+    assert_eq!(
+        plate.errors(),
+        [
+            ProtocolError::Busy {
+                instruction: b'A',
+                rs: true
+            },
+            ProtocolError::AddressOutOfRange(0x30),
+        ]
+    );
+
+    bus.set_slave_address(0x21).unwrap();
+    assert!(bus.block_write(0x00, &[0x00]).is_err());
+}
+
+#[test]
+fn buttons() {
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = display(&plate);
+    assert!(!display.is_pressed(Button::Up).unwrap());
+    plate.press(Button::Up);
+    assert!(display.is_pressed(Button::Up).unwrap());
+    assert!(!display.is_pressed(Button::Down).unwrap());
+    plate.release(Button::Up);
+    assert!(!display.is_pressed(Button::Up).unwrap());
+}
+
+#[test]
+fn button_interrupts() {
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = display(&plate);
+    display.enable_button_interrupts().unwrap();
+    assert!(!plate.interrupt());
+
+    // A quick tap, over before the interrupt is serviced.
+    plate.press(Button::Left);
+    plate.release(Button::Left);
+    assert!(plate.interrupt());
+    let mut captured = [false; 5];
+    captured[Button::Left as usize] = true;
+    assert_eq!(display.button_interrupt().unwrap(), Some(captured));
+    assert!(!plate.interrupt());
+    assert_eq!(display.button_interrupt().unwrap(), None);
+    assert_eq!(display.pressed_buttons().unwrap(), [false; 5]);
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+
+/// The content of the whole panel, one character per cell.
+///
+/// Frames are drawn off-screen, then sent to the panel with
+/// `AdafruitDisplay::render`, which only updates the cells that changed.
+/// Custom characters are represented by `'\u{0}'` to `'\u{7}'`, other
+/// characters are translated to the character ROM of the panel.
+///
+/// Frames implement `fmt::Write`, so `write!` draws text from where the
+/// last write stopped, or from the position given to `move_to`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    cols: u8,
+    lines: u8,
+    cells: Vec<char>,
+    cursor: (u8, u8),
+}
+
+impl Frame {
+    /// A blank frame for a panel of `cols` by `lines` characters.
+    pub fn new(cols: u8, lines: u8) -> Self {
+        Self {
+            cols,
+            lines,
+            cells: vec![' '; cols as usize * lines as usize],
+            cursor: (0, 0),
+        }
+    }
+
+    pub fn cols(&self) -> u8 {
+        self.cols
+    }
+
+    pub fn lines(&self) -> u8 {
+        self.lines
+    }
+
+    /// Blank the whole frame, and move back to its first cell.
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = ' ';
+        }
+        self.cursor = (0, 0);
+    }
+
+    /// Where the next `write!` starts.
+    pub fn move_to(&mut self, col: u8, line: u8) {
+        self.cursor = (col, line);
+    }
+
+    /// Position following the last character written.
+    pub fn cursor(&self) -> (u8, u8) {
+        self.cursor
+    }
+
+    /// Character at the given position, `None` if it is out of the frame.
+    pub fn get(&self, col: u8, line: u8) -> Option<char> {
+        if col < self.cols && line < self.lines {
+            Some(self.cells[line as usize * self.cols as usize + col as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Set the character at the given position, ignored if it is out of the frame.
+    pub fn set(&mut self, col: u8, line: u8, c: char) {
+        if col < self.cols && line < self.lines {
+            self.cells[line as usize * self.cols as usize + col as usize] = c;
+        }
+    }
+
+    /// Characters of a whole line, none if it is out of the frame.
+    pub fn line(&self, line: u8) -> &[char] {
+        if line >= self.lines {
+            return &[];
+        }
+        let start = line as usize * self.cols as usize;
+        &self.cells[start..start + self.cols as usize]
+    }
+
+    /// Write text from the given position, clipped to the frame. Like
+    /// `AdafruitDisplay::message`, newlines start over on the first column
+    /// of the next line.
+    pub fn write(&mut self, col: u8, line: u8, text: &str) {
+        let (mut col, mut line) = (col, line);
+        for c in text.chars() {
+            if c == '\n' {
+                col = 0;
+                line = line.saturating_add(1);
+            } else {
+                self.set(col, line, c);
+                col = col.saturating_add(1);
+            }
+        }
+        self.cursor = (col, line);
+    }
+
+    /// Write lines of text from the given position, one per line of the
+    /// frame, e.g. the ones laid out by `layout::wrap`.
+    pub fn write_lines<S: AsRef<str>>(&mut self, col: u8, line: u8, lines: &[S]) {
+        for (i, text) in lines.iter().enumerate() {
+            self.write(col, line.saturating_add(i as u8), text.as_ref());
+        }
+    }
+}
+
+impl fmt::Write for Frame {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let (col, line) = self.cursor;
+        self.write(col, line, s);
+        Ok(())
+    }
+}
+
+#[test]
+fn write() {
+    let mut frame = Frame::new(4, 2);
+    frame.write(2, 0, "abc\nde\nf");
+    assert_eq!(frame.line(0), [' ', ' ', 'a', 'b']);
+    assert_eq!(frame.line(1), ['d', 'e', ' ', ' ']);
+    assert_eq!(frame.get(1, 1), Some('e'));
+    assert_eq!(frame.get(4, 1), None);
+    assert!(frame.line(2).is_empty());
+
+    frame.clear();
+    assert_eq!(frame, Frame::new(4, 2));
+}
+
+#[test]
+fn formatting() {
+    use std::fmt::Write;
+
+    let mut frame = Frame::new(6, 2);
+    write!(frame, "{}%", 42).unwrap();
+    write!(frame, " ok\n{:>6}", 7).unwrap();
+    assert_eq!(frame.line(0).iter().collect::<String>(), "42% ok");
+    assert_eq!(frame.line(1).iter().collect::<String>(), "     7");
+
+    frame.move_to(1, 1);
+    write!(frame, "x").unwrap();
+    assert_eq!(frame.get(1, 1), Some('x'));
+    assert_eq!(frame.cursor(), (2, 1));
+}
@@ -0,0 +1,173 @@
+use crate::errors::CommunicationError;
+use crate::i2c::bus::I2cBus;
+use crate::i2c::io::MCP230xx;
+use crate::i2c::lcd::{AdafruitDisplay, CharacterRom};
+
+/// How the character cells of a panel map to the DDRAM of its HD44780.
+///
+/// On panels of 1 or 2 lines, each line is a window over 40 characters of
+/// memory (80 for a single line), which can be shifted. Panels of 4 lines
+/// split the 2 lines of memory in halves, the third line following the
+/// first one. Some 16x1 panels are wired as 8x2, their right half being
+/// the start of the second line of memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Geometry {
+    cols: u8,
+    lines: u8,
+    row_offsets: [u8; 4],
+    split: bool,
+}
+
+impl Geometry {
+    pub const LCD_16X1: Geometry = Geometry::new(16, 1);
+    pub const LCD_16X2: Geometry = Geometry::new(16, 2);
+    pub const LCD_20X2: Geometry = Geometry::new(20, 2);
+    pub const LCD_20X4: Geometry = Geometry::new(20, 4);
+    pub const LCD_40X2: Geometry = Geometry::new(40, 2);
+
+    /// A panel of `cols` by `lines` characters, with the usual row offsets:
+    /// `0x00`, `0x40`, then `cols` and `0x40 + cols` for the third and
+    /// fourth lines.
+    pub const fn new(cols: u8, lines: u8) -> Self {
+        let lines = if lines > 4 { 4 } else { lines };
+        Geometry {
+            cols,
+            lines,
+            row_offsets: [0x00, 0x40, cols, 0x40 + cols],
+            split: false,
+        }
+    }
+
+    /// Use other DDRAM addresses for the first column of each line.
+    pub const fn with_row_offsets(mut self, row_offsets: [u8; 4]) -> Self {
+        self.row_offsets = row_offsets;
+        self
+    }
+
+    /// A single line panel driven as 2 lines of half its width.
+    pub const fn split(mut self) -> Self {
+        self.split = true;
+        self
+    }
+
+    pub fn cols(&self) -> u8 {
+        self.cols
+    }
+
+    pub fn lines(&self) -> u8 {
+        self.lines
+    }
+
+    /// Whether the controller has to be set up for 2 lines of memory.
+    pub fn two_lines(&self) -> bool {
+        self.lines > 1 || self.split
+    }
+
+    /// Length of a line of DDRAM, which is how far the display can shift.
+    pub fn line_length(&self) -> u8 {
+        if self.two_lines() {
+            40
+        } else {
+            80
+        }
+    }
+
+    /// Number of columns of each line that can be written, including the
+    /// ones hidden until the display is shifted.
+    pub fn memory_cols(&self) -> u8 {
+        if self.split || self.lines > 2 {
+            self.cols
+        } else {
+            self.line_length().max(self.cols)
+        }
+    }
+
+    /// DDRAM address of the given cell, `None` if it is out of the panel or
+    /// past the addresses the row offsets leave.
+    pub fn address(&self, col: u8, line: u8) -> Option<u8> {
+        if line >= self.lines || col >= self.memory_cols() {
+            return None;
+        }
+        let half = self.cols / 2;
+        match self.split {
+            true if col >= half => (col - half).checked_add(0x40),
+            true => Some(col),
+            false => self.row_offsets[line as usize].checked_add(col),
+        }
+    }
+}
+
+/// Sets up an `AdafruitDisplay` for a panel of any geometry.
+#[derive(Clone, Debug)]
+pub struct DisplayBuilder {
+    geometry: Geometry,
+    backlight: bool,
+    invert_backlight_polarity: bool,
+    rom: CharacterRom,
+}
+
+impl DisplayBuilder {
+    /// A panel of the given geometry, with its backlight on and active low,
+    /// and the A00 character ROM.
+    pub fn new(geometry: Geometry) -> Self {
+        DisplayBuilder {
+            geometry,
+            backlight: true,
+            invert_backlight_polarity: true,
+            rom: CharacterRom::A00,
+        }
+    }
+
+    pub fn backlight(mut self, backlight: bool) -> Self {
+        self.backlight = backlight;
+        self
+    }
+
+    pub fn invert_backlight_polarity(mut self, invert: bool) -> Self {
+        self.invert_backlight_polarity = invert;
+        self
+    }
+
+    pub fn character_rom(mut self, rom: CharacterRom) -> Self {
+        self.rom = rom;
+        self
+    }
+
+    /// Initialize the panel, wired like the Adafruit plate to the given GPIO extender.
+    pub fn build<B: I2cBus>(
+        self,
+        gpio: MCP230xx<B>,
+    ) -> Result<AdafruitDisplay<B>, CommunicationError> {
+        AdafruitDisplay::new(
+            self.geometry,
+            self.backlight,
+            self.invert_backlight_polarity,
+            self.rom,
+            gpio,
+        )
+    }
+}
+
+#[test]
+fn addresses() {
+    let lcd = Geometry::LCD_20X4;
+    assert_eq!(lcd.address(0, 2), Some(0x14));
+    assert_eq!(lcd.address(19, 3), Some(0x67));
+    assert_eq!(lcd.address(20, 0), None);
+    assert_eq!(lcd.address(0, 4), None);
+
+    // Hidden columns can be written on 2 lines panels.
+    assert_eq!(Geometry::LCD_16X2.address(39, 1), Some(0x67));
+    assert_eq!(Geometry::LCD_16X2.address(40, 1), None);
+
+    let shifted = Geometry::LCD_20X4.with_row_offsets([0x00, 0x40, 0x14, 0xF0]);
+    assert_eq!(shifted.address(15, 3), Some(0xFF));
+    assert_eq!(shifted.address(16, 3), None);
+    assert_eq!(Geometry::LCD_40X2.address(39, 1), Some(0x67));
+
+    assert_eq!(Geometry::LCD_16X1.address(79, 0), Some(79));
+    let split = Geometry::LCD_16X1.split();
+    assert_eq!(split.address(7, 0), Some(0x07));
+    assert_eq!(split.address(8, 0), Some(0x40));
+    assert_eq!(split.address(16, 0), None);
+}
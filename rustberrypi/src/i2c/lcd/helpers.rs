@@ -1,15 +1,19 @@
 use bmp;
 use crate::errors::CommunicationError;
+use crate::i2c::bus::I2cBus;
 use crate::i2c::lcd::AdafruitDisplay;
 
 use std::path::PathBuf;
 use std::{thread, time};
 
-pub fn load_characters_from_bmp(
-    display: &mut AdafruitDisplay,
+/// Define the 8 glyphs of a 16x16 pixels image as `name0` to `name7`, the
+/// top half being the first 4 ones.
+pub fn load_characters_from_bmp<B: I2cBus>(
+    display: &mut AdafruitDisplay<B>,
+    name: &str,
     filepath: PathBuf,
-) -> Result<(), CommunicationError> {
-    let img = bmp::open(filepath).map_err(|e| CommunicationError::BitmapError(e))?;
+) -> Result<[char; 8], CommunicationError> {
+    let img = bmp::open(filepath).map_err(CommunicationError::BitmapError)?;
 
     let mut data = [
         [0; 8], [0; 8], [0; 8], [0; 8], [0; 8], [0; 8], [0; 8], [0; 8],
@@ -28,12 +32,14 @@ pub fn load_characters_from_bmp(
         }
     }
 
-    load_16px_block(display, data)
+    Ok(load_16px_block(display, name, data))
 }
 
-pub fn load_ferris(display: &mut AdafruitDisplay) -> Result<(), CommunicationError> {
+/// Define the glyphs of Ferris, to be drawn on 2 lines of 4 characters.
+pub fn load_ferris<B: I2cBus>(display: &mut AdafruitDisplay<B>) -> [char; 8] {
     load_16px_block(
         display,
+        "ferris",
         [
             [0x0, 0x3, 0xf, 0xf, 0xf, 0x1e, 0x18, 0x18],
             [0x0, 0x18, 0x0, 0x18, 0x10, 0x0, 0x0, 0x0],
@@ -47,14 +53,16 @@ pub fn load_ferris(display: &mut AdafruitDisplay) -> Result<(), CommunicationErr
     )
 }
 
-pub fn load_16px_block(
-    display: &mut AdafruitDisplay,
+pub fn load_16px_block<B: I2cBus>(
+    display: &mut AdafruitDisplay<B>,
+    name: &str,
     data: [[u8; 8]; 8],
-) -> Result<(), CommunicationError> {
+) -> [char; 8] {
+    let mut chars = [' '; 8];
     for (i, block) in data.iter().enumerate() {
-        display.create_char(i as u8, *block)?;
+        chars[i] = display.define_glyph(&format!("{}{}", name, i), *block);
     }
-    Ok(())
+    chars
 }
 
 pub fn delay_microseconds(duration: u64) {
@@ -0,0 +1,149 @@
+//! Laying out text in the few columns of a panel. Widths are counted in
+//! characters, each one taking a cell.
+
+/// Shown at the end of truncated text. Panels without it in their ROM get
+/// a custom character, or a dot.
+pub const ELLIPSIS: char = '…';
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// A column of a table, see `columns`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Column {
+    pub width: u8,
+    pub align: Align,
+}
+
+impl Column {
+    pub fn new(width: u8, align: Align) -> Self {
+        Column { width, align }
+    }
+}
+
+fn width_of(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Cut the text to `width` characters, ending it with an ellipsis when
+/// something was left out.
+pub fn truncate(text: &str, width: u8) -> String {
+    let width = width as usize;
+    if width_of(text) <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        truncated.push(ELLIPSIS);
+    }
+    truncated
+}
+
+/// Pad the text to exactly `width` characters, truncating it if needed.
+/// Centered text leans to the left when it can't be exactly centered.
+pub fn align(text: &str, width: u8, align: Align) -> String {
+    let text = truncate(text, width);
+    let padding = width as usize - width_of(&text);
+    let (left, right) = match align {
+        Align::Left => (0, padding),
+        Align::Center => (padding / 2, padding - padding / 2),
+        Align::Right => (padding, 0),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+/// A label on the left and a value pushed to the right edge. The label is
+/// truncated first when both don't fit, keeping a space between them.
+pub fn label_value(label: &str, value: &str, width: u8) -> String {
+    let value = truncate(value, width);
+    let room = (width as usize).saturating_sub(width_of(&value) + 1);
+    let label = if room == 0 {
+        String::new()
+    } else {
+        truncate(label, room as u8)
+    };
+    let padding = width as usize - width_of(&label) - width_of(&value);
+    format!("{}{}{}", label, " ".repeat(padding), value)
+}
+
+/// Lay out the values in fixed width columns, separated by a space.
+/// Missing values leave their column blank.
+pub fn columns(columns: &[Column], values: &[&str]) -> String {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| align(values.get(i).unwrap_or(&""), column.width, column.align))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Break the text into lines of at most `width` characters, between words
+/// when possible. Newlines in the text are kept, words longer than a line
+/// are split.
+pub fn wrap(text: &str, width: u8) -> Vec<String> {
+    let width = (width as usize).max(1);
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            let used = width_of(&line);
+            if used > 0 && used + 1 + word.len() <= width {
+                line.push(' ');
+                line.extend(&word);
+                continue;
+            }
+            if used > 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            while word.len() > width {
+                lines.push(word.drain(..width).collect());
+            }
+            line.extend(&word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[test]
+fn alignment() {
+    assert_eq!(align("DNS", 8, Align::Left), "DNS     ");
+    assert_eq!(align("DNS", 8, Align::Center), "  DNS   ");
+    assert_eq!(align("DNS", 8, Align::Right), "     DNS");
+    assert_eq!(align("doubleclick.net", 8, Align::Right), "doublec…");
+    assert_eq!(truncate("pi.hole", 0), "");
+    assert_eq!(truncate("pi.hole", 7), "pi.hole");
+}
+
+#[test]
+fn labels() {
+    assert_eq!(label_value("Queries", "123456", 16), "Queries   123456");
+    assert_eq!(label_value("Blocked domains", "98765", 16), "Blocked d… 98765");
+    assert_eq!(label_value("Queries", "12345678901234567", 16), "123456789012345…");
+    assert_eq!(
+        columns(
+            &[Column::new(6, Align::Left), Column::new(4, Align::Right)],
+            &["router", "1234"]
+        ),
+        "router 1234"
+    );
+    assert_eq!(
+        columns(&[Column::new(3, Align::Left), Column::new(2, Align::Right)], &["a"]),
+        "a     "
+    );
+}
+
+#[test]
+fn wrapping() {
+    assert_eq!(
+        wrap("Pi-hole is unreachable, will retry", 16),
+        ["Pi-hole is", "unreachable,", "will retry"]
+    );
+    assert_eq!(wrap("doubleclick.net", 6), ["double", "click.", "net"]);
+    assert_eq!(wrap("a\n\nb c", 3), ["a", "", "b c"]);
+}
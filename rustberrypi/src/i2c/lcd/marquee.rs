@@ -0,0 +1,217 @@
+use crate::i2c::lcd::Frame;
+
+use std::time::Duration;
+
+/// Blank columns between the end of the text and its start again, when wrapping.
+const WRAP_GAP: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScrollMode {
+    /// Scroll to the end of the text, then back to its start.
+    Bounce,
+    /// Scroll past the end of the text, which starts over after a gap.
+    Wrap,
+}
+
+/// Scrolls text longer than the space it's shown in. Marquees don't keep
+/// time: the position is computed from the time elapsed since the text was
+/// first shown, so that redrawing a frame at any rate shows it in the
+/// right place.
+///
+/// A single line is scrolled by drawing the visible part of the text with
+/// `write`. To scroll the whole screen, lay out the text given by
+/// `scrolled_text` in a wide frame, and pass it with `offset` to
+/// `AdafruitDisplay::render_scrolled`, which shifts the display in hardware
+/// when it can.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marquee {
+    text: Vec<char>,
+    width: u8,
+    step: Duration,
+    pause: Duration,
+    mode: ScrollMode,
+}
+
+impl Marquee {
+    /// Scroll the text in a space of `width` columns, by one column every
+    /// 300 ms, pausing 1.5 s at each end.
+    pub fn new(text: &str, width: u8) -> Self {
+        Marquee {
+            text: text.chars().collect(),
+            width,
+            step: Duration::from_millis(300),
+            pause: Duration::from_millis(1500),
+            mode: ScrollMode::Bounce,
+        }
+    }
+
+    /// How long each column stays before scrolling to the next one.
+    pub fn speed(mut self, step: Duration) -> Self {
+        self.step = step.max(Duration::from_millis(1));
+        self
+    }
+
+    /// How long the text stays still at each end.
+    pub fn pause(mut self, pause: Duration) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    pub fn mode(mut self, mode: ScrollMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Whether the text is too long to be shown at once.
+    pub fn is_scrolling(&self) -> bool {
+        self.text.len() > self.width as usize
+    }
+
+    /// The text the window moves over: when wrapping, the text followed by
+    /// a gap and its start again.
+    pub fn scrolled_text(&self) -> String {
+        match self.mode {
+            ScrollMode::Wrap if self.is_scrolling() => {
+                let mut text = self.text.clone();
+                text.extend(std::iter::repeat_n(' ', WRAP_GAP));
+                text.extend(&self.text[..self.width as usize]);
+                text.into_iter().collect()
+            }
+            _ => self.text.iter().collect(),
+        }
+    }
+
+    /// Column of the scrolled text shown first, once `elapsed` passed since
+    /// the text was first shown.
+    pub fn offset(&self, elapsed: Duration) -> usize {
+        if !self.is_scrolling() {
+            return 0;
+        }
+        let (step, pause) = (self.step.as_nanos(), self.pause.as_nanos());
+        let steps = |time: u128| (time / step) as usize;
+        match self.mode {
+            ScrollMode::Bounce => {
+                let overflow = self.text.len() - self.width as usize;
+                let travel = overflow as u128 * step;
+                let time = elapsed.as_nanos() % (2 * (pause + travel));
+                if time < pause {
+                    0
+                } else if time < pause + travel {
+                    steps(time - pause).min(overflow)
+                } else if time < 2 * pause + travel {
+                    overflow
+                } else {
+                    overflow - steps(time - 2 * pause - travel).min(overflow)
+                }
+            }
+            ScrollMode::Wrap => {
+                let period = self.text.len() + WRAP_GAP;
+                let time = elapsed.as_nanos() % (pause + period as u128 * step);
+                if time < pause {
+                    0
+                } else {
+                    steps(time - pause).min(period - 1)
+                }
+            }
+        }
+    }
+
+    /// The part of the text that is visible.
+    pub fn window(&self, elapsed: Duration) -> String {
+        self.scrolled_text()
+            .chars()
+            .skip(self.offset(elapsed))
+            .take(self.width as usize)
+            .collect()
+    }
+
+    /// Draw the visible part of the text, padded to the width of the marquee.
+    pub fn write(&self, frame: &mut Frame, col: u8, line: u8, elapsed: Duration) {
+        let window = format!(
+            "{:width$}",
+            self.window(elapsed),
+            width = self.width as usize
+        );
+        frame.write(col, line, &window);
+    }
+}
+
+#[test]
+fn bounce() {
+    let marquee = Marquee::new("pi.hole.example", 10)
+        .speed(Duration::from_secs(1))
+        .pause(Duration::from_secs(2));
+    let at = |secs| marquee.window(Duration::from_secs(secs));
+    assert_eq!(at(0), "pi.hole.ex");
+    assert_eq!(at(2), "pi.hole.ex");
+    assert_eq!(at(3), "i.hole.exa");
+    assert_eq!(at(6), "ole.exampl");
+    assert_eq!(at(7), "le.example");
+    // Back to the start after pausing at the end.
+    assert_eq!(at(9), "le.example");
+    assert_eq!(at(10), "ole.exampl");
+    assert_eq!(at(14), "pi.hole.ex");
+    assert_eq!(at(16), "pi.hole.ex");
+    assert_eq!(at(17), "i.hole.exa");
+
+    // Short text stays still.
+    assert_eq!(
+        Marquee::new("pi.hole", 10).offset(Duration::from_secs(5)),
+        0
+    );
+}
+
+#[test]
+fn long_text() {
+    // Longer than 255 characters, e.g. a domain name along with a prefix.
+    let text = format!("{}.example", "a".repeat(300));
+    let marquee = Marquee::new(&text, 16)
+        .speed(Duration::from_secs(1))
+        .pause(Duration::from_secs(0));
+    assert_eq!(marquee.offset(Duration::from_secs(290)), 290);
+    assert_eq!(marquee.window(Duration::from_secs(292)), "aaaaaaaa.example");
+}
+
+#[test]
+fn wrap() {
+    let marquee = Marquee::new("abcdef", 4)
+        .mode(ScrollMode::Wrap)
+        .speed(Duration::from_secs(1))
+        .pause(Duration::from_secs(1));
+    assert_eq!(marquee.scrolled_text(), "abcdef   abcd");
+    let windows: Vec<String> = (0..11)
+        .map(|secs| marquee.window(Duration::from_secs(secs)))
+        .collect();
+    assert_eq!(
+        windows,
+        ["abcd", "abcd", "bcde", "cdef", "def ", "ef  ", "f   ", "   a", "  ab", " abc", "abcd"]
+    );
+}
+
+#[test]
+fn hardware_shift() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let mut frame = Frame::new(24, 2);
+    frame.write(0, 0, "doubleclick.example.net\nads.example");
+    display.render_scrolled(&frame, 0).unwrap();
+    assert_eq!(plate.text(), ["doubleclick.exam", "ads.example     "]);
+
+    // Scrolling by one column only takes one instruction.
+    let count = plate.lcd().instruction_count();
+    display.render_scrolled(&frame, 1).unwrap();
+    assert_eq!(plate.text(), ["oubleclick.examp", "ds.example      "]);
+    assert_eq!(plate.lcd().instruction_count() - count, 1);
+
+    // Rendering a frame shifts back, and the rest of DDRAM is untouched.
+    display.render_scrolled(&frame, 7).unwrap();
+    let mut small = display.frame();
+    small.write(0, 0, "Blocked");
+    display.render(&small).unwrap();
+    assert_eq!(plate.text(), ["Blocked         ", "                "]);
+    assert_eq!(plate.lcd().display_shift(), 0);
+    assert_eq!(&plate.lcd().ddram()[16..23], b"ple.net");
+    assert!(plate.errors().is_empty());
+}
@@ -0,0 +1,441 @@
+use crate::i2c::lcd::buttons::ButtonEvent;
+use crate::i2c::lcd::layout::{self, Align};
+use crate::i2c::lcd::{Button, Frame};
+
+use std::ops::RangeInclusive;
+
+/// Marks the selected item, both arrows are in the ROMs of the panels.
+const SELECTED: char = '→';
+const BACK: char = '←';
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind<K> {
+    Submenu(Vec<Item<K>>),
+    Action(K),
+    Checkbox(K, bool),
+    Stepper {
+        key: K,
+        value: i32,
+        range: RangeInclusive<i32>,
+        step: i32,
+        unit: String,
+    },
+    Confirm(K),
+}
+
+/// An entry of a `Menu`, identified by a key of the application's choosing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item<K> {
+    label: String,
+    kind: Kind<K>,
+}
+
+impl<K> Item<K> {
+    pub fn submenu(label: &str, items: Vec<Item<K>>) -> Self {
+        Item {
+            label: label.to_string(),
+            kind: Kind::Submenu(items),
+        }
+    }
+
+    pub fn action(label: &str, key: K) -> Self {
+        Item {
+            label: label.to_string(),
+            kind: Kind::Action(key),
+        }
+    }
+
+    pub fn checkbox(label: &str, key: K, checked: bool) -> Self {
+        Item {
+            label: label.to_string(),
+            kind: Kind::Checkbox(key, checked),
+        }
+    }
+
+    /// A number picked in `range`, by steps of 1.
+    pub fn stepper(label: &str, key: K, value: i32, range: RangeInclusive<i32>) -> Self {
+        Item {
+            label: label.to_string(),
+            kind: Kind::Stepper {
+                key,
+                value: value.clamp(*range.start(), *range.end()),
+                range,
+                step: 1,
+                unit: String::new(),
+            },
+        }
+    }
+
+    /// An action that has to be confirmed first.
+    pub fn confirm(label: &str, key: K) -> Self {
+        Item {
+            label: label.to_string(),
+            kind: Kind::Confirm(key),
+        }
+    }
+
+    /// Change a stepper by `step` at a time.
+    pub fn step(mut self, step: i32) -> Self {
+        if let Kind::Stepper {
+            step: ref mut s, ..
+        } = self.kind
+        {
+            *s = step.max(1);
+        }
+        self
+    }
+
+    /// Show a stepper's value followed by a unit.
+    pub fn unit(mut self, unit: &str) -> Self {
+        if let Kind::Stepper {
+            unit: ref mut u, ..
+        } = self.kind
+        {
+            *u = unit.to_string();
+        }
+        self
+    }
+
+    fn key(&self) -> Option<&K> {
+        match self.kind {
+            Kind::Submenu(_) => None,
+            Kind::Action(ref key) | Kind::Checkbox(ref key, _) | Kind::Confirm(ref key) => {
+                Some(key)
+            }
+            Kind::Stepper { ref key, .. } => Some(key),
+        }
+    }
+}
+
+/// What the operator did in a `Menu`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuEvent<K> {
+    /// An action was run, or confirmed.
+    Action(K),
+    Toggled(K, bool),
+    /// A new value was picked with a stepper.
+    Changed(K, i32),
+    /// Left was pressed on the top level menu.
+    Closed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Browse,
+    /// Changing a stepper, its value before editing is restored on Left.
+    Edit(i32),
+    Confirm,
+}
+
+/// A menu driven by the plate buttons: Up and Down move through the
+/// items, Right or Select enter a submenu or run an item, Left goes back.
+///
+/// Checkboxes are toggled right away. Steppers are changed with Up and
+/// Down once entered, then kept with Right or Select, or restored with
+/// Left. Confirmation items ask first, Right or Select confirming.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Menu<K> {
+    items: Vec<Item<K>>,
+    /// The selected item at each level, the last one being shown.
+    path: Vec<usize>,
+    mode: Mode,
+}
+
+impl<K: Clone + PartialEq> Menu<K> {
+    pub fn new(items: Vec<Item<K>>) -> Self {
+        Menu {
+            items,
+            path: vec![0],
+            mode: Mode::Browse,
+        }
+    }
+
+    /// Go back to the first item of the top level.
+    pub fn reset(&mut self) {
+        self.path = vec![0];
+        self.mode = Mode::Browse;
+    }
+
+    /// Check or uncheck a checkbox, when the setting changed elsewhere.
+    pub fn set_checked(&mut self, key: &K, checked: bool) {
+        if let Some(Item {
+            kind: Kind::Checkbox(_, ref mut c),
+            ..
+        }) = find(&mut self.items, key)
+        {
+            *c = checked;
+        }
+    }
+
+    /// Set the value of a stepper, clamped to its range.
+    pub fn set_value(&mut self, key: &K, value: i32) {
+        if let Some(Item {
+            kind:
+                Kind::Stepper {
+                    value: ref mut v,
+                    ref range,
+                    ..
+                },
+            ..
+        }) = find(&mut self.items, key)
+        {
+            *v = value.clamp(*range.start(), *range.end());
+        }
+    }
+
+    /// Items of the level shown.
+    fn level(&self) -> &[Item<K>] {
+        let mut items = &self.items[..];
+        for index in &self.path[..self.path.len() - 1] {
+            if let Kind::Submenu(ref sub) = items[*index].kind {
+                items = sub;
+            }
+        }
+        items
+    }
+
+    fn selected(&self) -> Option<&Item<K>> {
+        self.level().get(*self.path.last().unwrap())
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut Item<K>> {
+        let (last, parents) = self.path.split_last().unwrap();
+        let mut items = &mut self.items;
+        for index in parents {
+            match items[*index].kind {
+                Kind::Submenu(ref mut sub) => items = sub,
+                _ => return None,
+            }
+        }
+        items.get_mut(*last)
+    }
+
+    /// Handle a button event. Held Up and Down buttons repeat, if the
+    /// events are set up to.
+    pub fn on_button(&mut self, event: ButtonEvent) -> Option<MenuEvent<K>> {
+        let button = match event {
+            ButtonEvent::Pressed(button) => button,
+            ButtonEvent::Repeat(button @ Button::Up)
+            | ButtonEvent::Repeat(button @ Button::Down) => button,
+            _ => return None,
+        };
+        match self.mode {
+            Mode::Browse => self.browse(button),
+            Mode::Edit(original) => self.edit(button, original),
+            Mode::Confirm => {
+                self.mode = Mode::Browse;
+                match button {
+                    Button::Right | Button::Select => self
+                        .selected()
+                        .and_then(|item| item.key().cloned())
+                        .map(MenuEvent::Action),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn browse(&mut self, button: Button) -> Option<MenuEvent<K>> {
+        let count = self.level().len();
+        let selected = self.path.last_mut().unwrap();
+        match button {
+            Button::Up => *selected = (*selected + count.max(1) - 1) % count.max(1),
+            Button::Down => *selected = (*selected + 1) % count.max(1),
+            Button::Left => {
+                if self.path.len() == 1 {
+                    return Some(MenuEvent::Closed);
+                }
+                self.path.pop();
+            }
+            Button::Right | Button::Select => {
+                let item = self.selected_mut()?;
+                match item.kind {
+                    Kind::Submenu(_) => self.path.push(0),
+                    Kind::Action(ref key) => return Some(MenuEvent::Action(key.clone())),
+                    Kind::Checkbox(ref key, ref mut checked) => {
+                        *checked = !*checked;
+                        return Some(MenuEvent::Toggled(key.clone(), *checked));
+                    }
+                    Kind::Stepper { value, .. } => self.mode = Mode::Edit(value),
+                    Kind::Confirm(_) => self.mode = Mode::Confirm,
+                }
+            }
+        }
+        None
+    }
+
+    fn edit(&mut self, button: Button, original: i32) -> Option<MenuEvent<K>> {
+        let (key, value, range, step) = match self.selected_mut()?.kind {
+            Kind::Stepper {
+                ref key,
+                ref mut value,
+                ref range,
+                step,
+                ..
+            } => (key.clone(), value, range.clone(), step),
+            _ => return None,
+        };
+        match button {
+            Button::Up => *value = (*value + step).min(*range.end()),
+            Button::Down => *value = (*value - step).max(*range.start()),
+            Button::Left => {
+                *value = original;
+                self.mode = Mode::Browse;
+            }
+            Button::Right | Button::Select => {
+                let value = *value;
+                self.mode = Mode::Browse;
+                if value != original {
+                    return Some(MenuEvent::Changed(key, value));
+                }
+            }
+        }
+        None
+    }
+
+    /// Draw the level shown over the whole frame, scrolled to the selected item.
+    pub fn draw(&self, frame: &mut Frame) {
+        frame.clear();
+        let cols = frame.cols();
+        if self.mode == Mode::Confirm {
+            let label = self.selected().map_or("", |item| &item.label);
+            frame.write(
+                0,
+                0,
+                &layout::align(&format!("{}?", label), cols, Align::Center),
+            );
+            let answers =
+                layout::label_value(&format!("{}No", BACK), &format!("Yes{}", SELECTED), cols);
+            frame.write(0, frame.lines().saturating_sub(1), &answers);
+            return;
+        }
+
+        let items = self.level();
+        let selected = *self.path.last().unwrap();
+        let lines = frame.lines().max(1) as usize;
+        let first = selected.saturating_sub(lines - 1);
+        for (line, (index, item)) in items.iter().enumerate().skip(first).take(lines).enumerate() {
+            let marker = if index == selected { SELECTED } else { ' ' };
+            let value = match item.kind {
+                Kind::Checkbox(_, true) => "[x]".to_string(),
+                Kind::Checkbox(_, false) => "[ ]".to_string(),
+                Kind::Stepper {
+                    value, ref unit, ..
+                } => match self.mode {
+                    Mode::Edit(_) if index == selected => format!("<{}{}>", value, unit),
+                    _ => format!("{}{}", value, unit),
+                },
+                Kind::Submenu(_) => ">".to_string(),
+                Kind::Action(_) | Kind::Confirm(_) => String::new(),
+            };
+            let text = layout::label_value(&item.label, &value, cols.saturating_sub(1));
+            frame.write(0, line as u8, &format!("{}{}", marker, text));
+        }
+    }
+}
+
+/// The item with the given key, in any submenu.
+fn find<'a, K: PartialEq>(items: &'a mut [Item<K>], key: &K) -> Option<&'a mut Item<K>> {
+    for item in items.iter_mut() {
+        if let Kind::Submenu(ref mut sub) = item.kind {
+            if let Some(found) = find(sub, key) {
+                return Some(found);
+            }
+        } else if item.key() == Some(key) {
+            return Some(item);
+        }
+    }
+    None
+}
+
+#[test]
+fn navigation() {
+    #[derive(Clone, Debug, PartialEq)]
+    enum Key {
+        Backlight,
+        Time,
+        Refresh,
+        Quit,
+    }
+
+    let mut menu = Menu::new(vec![
+        Item::submenu(
+            "Display",
+            vec![
+                Item::checkbox("Backlight", Key::Backlight, true),
+                Item::stepper("Time", Key::Time, 10, 3..=60)
+                    .step(5)
+                    .unit("s"),
+            ],
+        ),
+        Item::action("Refresh", Key::Refresh),
+        Item::confirm("Quit", Key::Quit),
+    ]);
+    let mut frame = Frame::new(16, 2);
+    let press = |menu: &mut Menu<Key>, button| menu.on_button(ButtonEvent::Pressed(button));
+    let text = |menu: &Menu<Key>, frame: &mut Frame| -> Vec<String> {
+        menu.draw(frame);
+        (0..frame.lines())
+            .map(|l| frame.line(l).iter().collect())
+            .collect()
+    };
+
+    assert_eq!(
+        text(&menu, &mut frame),
+        ["→Display       >", " Refresh        "]
+    );
+    assert_eq!(press(&mut menu, Button::Right), None);
+    assert_eq!(
+        press(&mut menu, Button::Select),
+        Some(MenuEvent::Toggled(Key::Backlight, false))
+    );
+    assert_eq!(
+        text(&menu, &mut frame),
+        ["→Backlight   [ ]", " Time        10s"]
+    );
+
+    // Steppers are kept with Select, restored with Left.
+    press(&mut menu, Button::Down);
+    press(&mut menu, Button::Select);
+    press(&mut menu, Button::Up);
+    assert_eq!(text(&menu, &mut frame)[1], "→Time      <15s>");
+    assert_eq!(
+        press(&mut menu, Button::Select),
+        Some(MenuEvent::Changed(Key::Time, 15))
+    );
+    press(&mut menu, Button::Select);
+    for _ in 0..3 {
+        menu.on_button(ButtonEvent::Repeat(Button::Down));
+    }
+    press(&mut menu, Button::Left);
+    assert_eq!(text(&menu, &mut frame)[1], "→Time        15s");
+
+    // Back to the top level, scrolled to the selected item.
+    press(&mut menu, Button::Left);
+    press(&mut menu, Button::Up);
+    assert_eq!(
+        text(&menu, &mut frame),
+        [" Refresh        ", "→Quit           "]
+    );
+    press(&mut menu, Button::Select);
+    assert_eq!(
+        text(&menu, &mut frame),
+        ["     Quit?      ", "←No         Yes→"]
+    );
+    assert_eq!(press(&mut menu, Button::Left), None);
+    press(&mut menu, Button::Select);
+    assert_eq!(
+        press(&mut menu, Button::Right),
+        Some(MenuEvent::Action(Key::Quit))
+    );
+    assert_eq!(press(&mut menu, Button::Left), Some(MenuEvent::Closed));
+
+    menu.set_checked(&Key::Backlight, true);
+    menu.set_value(&Key::Time, 100);
+    menu.reset();
+    press(&mut menu, Button::Right);
+    assert_eq!(
+        text(&menu, &mut frame),
+        ["→Backlight   [x]", " Time        60s"]
+    );
+}
@@ -1,18 +1,42 @@
 use std::char;
-
+use std::fmt;
+
+pub mod bar;
+pub mod bignum;
+pub mod buttons;
+pub mod cgram;
+pub mod charset;
+pub mod emulator;
+pub mod frame;
+pub mod geometry;
 pub mod helpers;
+pub mod layout;
+pub mod marquee;
+pub mod menu;
+pub mod sparkline;
+
+pub use self::charset::CharacterRom;
+pub use self::frame::Frame;
+pub use self::geometry::{DisplayBuilder, Geometry};
+pub use self::layout::Align;
+pub use self::marquee::{Marquee, ScrollMode};
+pub use self::menu::{Item, Menu, MenuEvent};
 
 use crate::errors::CommunicationError;
-use crate::i2c::io::MCP230xx;
+use crate::i2c::bus::I2cBus;
+use crate::i2c::io::{Interrupt, MCP230xx};
+
+use self::cgram::GlyphCache;
 
 use rppal::gpio::{ Mode, Level, PullUpDown };
+use rppal::i2c::I2c;
 
 // Commands
 const LCD_CLEARDISPLAY: u8 = 0x01;
 const LCD_RETURNHOME: u8 = 0x02;
 const LCD_ENTRYMODESET: u8 = 0x04;
 const LCD_DISPLAYCONTROL: u8 = 0x08;
-const _LCD_CURSORSHIFT: u8 = 0x10;
+const LCD_CURSORSHIFT: u8 = 0x10;
 const LCD_FUNCTIONSET: u8 = 0x20;
 const LCD_SETCGRAMADDR: u8 = 0x40;
 const LCD_SETDDRAMADDR: u8 = 0x80;
@@ -32,10 +56,10 @@ const LCD_BLINKON: u8 = 0x01;
 const LCD_BLINKOFF: u8 = 0x00;
 
 // Move flags
-const _LCD_DISPLAYMOVE: u8 = 0x08;
+const LCD_DISPLAYMOVE: u8 = 0x08;
 const _LCD_CURSORMOVE: u8 = 0x00;
-const _LCD_MOVERIGHT: u8 = 0x04;
-const _LCD_MOVELEFT: u8 = 0x00;
+const LCD_MOVERIGHT: u8 = 0x04;
+const LCD_MOVELEFT: u8 = 0x00;
 
 // Function set flags
 const _LCD_8BITMODE: u8 = 0x10;
@@ -58,7 +82,7 @@ const LCD_PLATE_GREEN: u8 = 7;
 const LCD_PLATE_BLUE: u8 = 8;
 const _LCD_BACKPACK_LITE: u8 = 7;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Button {
     Select = 0,
     Right = 1,
@@ -67,7 +91,17 @@ pub enum Button {
     Left = 4,
 }
 
-pub struct AdafruitDisplay {
+impl Button {
+    pub const ALL: [Button; 5] = [
+        Button::Select,
+        Button::Right,
+        Button::Down,
+        Button::Up,
+        Button::Left,
+    ];
+}
+
+pub struct AdafruitDisplay<B: I2cBus = I2c> {
     rs: u8,
     en: u8,
     d4: u8,
@@ -79,46 +113,41 @@ pub struct AdafruitDisplay {
     displaymode: u8,
     backlight: bool,
     blpol: bool,
-    gpio: MCP230xx,
-    cols: u8,
-    lines: u8,
+    gpio: MCP230xx<B>,
+    geometry: Geometry,
+    color: (u8, u8, u8),
+    rom: CharacterRom,
+    /// What the panel is showing, as far as we know, one character code per
+    /// cell. It covers `Geometry::memory_cols`, including the columns hidden
+    /// until the display is shifted.
+    shadow: Frame,
+    /// How many columns the display was shifted to the left.
+    shift: u8,
+    /// Position of the address counter, `None` when it isn't on a visible
+    /// cell or can't be known.
+    cursor: Option<(u8, u8)>,
+    glyphs: GlyphCache,
 }
 
 /// Based on the [Python driver by Adafruit](https://github.com/adafruit/Adafruit_Python_CharLCD)
-impl AdafruitDisplay {
-    /// Initialize the LCD.  RS, EN, and D4...D7 parameters should be the pins
-    /// connected to the LCD RS, clock enable, and data line 4 through 7 connections.
-    ///
-    /// The LCD will be used in its 4-bit mode so these 6 lines are the only ones
-    /// required to use the LCD.  You must also pass in the number of columns and
-    /// lines on the LCD.  
+impl<B: I2cBus> AdafruitDisplay<B> {
+    /// Initialize the LCD, wired like the Adafruit plate to `gpio`: the
+    /// MCP23017 extender on its I2C bus drives the RS, EN and D4 to D7 lines
+    /// of the LCD, used in its 4-bit mode, and reads the buttons.
     ///
-    /// If you would like to control the backlight, pass in the pin connected to
-    /// the backlight with the backlight parameter. The invert_polarity boolean
-    /// controls if the backlight is one with a LOW signal or HIGH signal.  The
-    /// default invert_polarity value is true, i.e. the backlight is on with a
-    /// LOW signal.
+    /// The `geometry` tells how many columns and lines the panel has, and how
+    /// they're addressed. The `rom` is the character generator ROM the
+    /// HD44780 was manufactured with, used to encode the text.
     ///
-    /// You can enable PWM of the backlight pin to have finer control on the
-    /// brightness.  To enable PWM make sure your hardware supports PWM on the
-    /// provided backlight pin and set enable_pwm to True (the default is False).
-    /// The appropriate PWM library will be used depending on the platform, but
-    /// you can provide an explicit one with the pwm parameter.
-    ///
-    /// The initial state of the backlight is ON, but you can set it to an
-    /// explicit initial state with the initial_backlight parameter (0 is off,
-    /// 1 is on/full bright).
-    ///
-    /// You can optionally pass in an explicit GPIO class,
-    /// for example if you want to use an MCP230xx GPIO extender.  If you don't
-    /// pass in an GPIO instance, the default GPIO for the running platform will
-    /// be used.
-    fn new(
-        cols: u8,
-        lines: u8,
+    /// The backlight is switched on in white when `backlight` is set, and
+    /// `invert_backlight_polarity` tells that it's on with a LOW signal, as on
+    /// the plate. Use `DisplayBuilder` to set all of these up.
+    pub(crate) fn new(
+        geometry: Geometry,
         backlight: bool,
         invert_backlight_polarity: bool,
-        gpio: MCP230xx,
+        rom: CharacterRom,
+        gpio: MCP230xx<B>,
     ) -> Result<Self, CommunicationError> {
         let mut display = Self {
             rs: LCD_PLATE_RS,
@@ -128,46 +157,22 @@ impl AdafruitDisplay {
             d6: LCD_PLATE_D6,
             d7: LCD_PLATE_D7,
             displaycontrol: LCD_DISPLAYON | LCD_CURSOROFF | LCD_BLINKOFF,
-            displayfunction: LCD_4BITMODE | LCD_1LINE | LCD_2LINE | LCD_5X8DOTS,
+            displayfunction: LCD_4BITMODE
+                | if geometry.two_lines() { LCD_2LINE } else { LCD_1LINE }
+                | LCD_5X8DOTS,
             displaymode: LCD_ENTRYLEFT | LCD_ENTRYSHIFTDECREMENT,
-            backlight: backlight,
-            gpio: gpio,
+            backlight,
+            gpio,
             blpol: !invert_backlight_polarity,
-            cols,
-            lines,
+            geometry,
+            color: (0, 0, 0),
+            rom,
+            shadow: Frame::new(geometry.memory_cols(), geometry.lines()),
+            shift: 0,
+            cursor: None,
+            glyphs: GlyphCache::default(),
         };
-        display.gpio.setup(LCD_PLATE_RW, Mode::Output)?;
-        display.gpio.output(LCD_PLATE_RW, false)?;
-        for button in &[
-            Button::Select,
-            Button::Right,
-            Button::Down,
-            Button::Up,
-            Button::Left,
-        ] {
-            display.gpio.setup((*button).clone() as u8, Mode::Input)?;
-            display
-                .gpio
-                .pullup((*button).clone() as u8, PullUpDown::PullUp)?;
-        }
-
-        // Setup all pins as OUTPUT
-        for pin in &[
-            display.rs, display.en, display.d4, display.d5, display.d6, display.d7,
-        ] {
-            display.gpio.setup(*pin, Mode::Output)?;
-        }
-
-        display.write8(0x33, false)?;
-        display.write8(0x32, false)?;
-
-        let displaycontrol = LCD_DISPLAYCONTROL | display.displaycontrol;
-        let displayfunction = LCD_FUNCTIONSET | display.displayfunction;
-        let displaymode = LCD_ENTRYMODESET | display.displaymode;
-        display.write8(displaycontrol, false)?;
-        display.write8(displayfunction, false)?;
-        display.write8(displaymode, false)?;
-        display.clear()?;
+        display.initialize()?;
 
         // Setup backlight pins
         if display.backlight {
@@ -179,9 +184,221 @@ impl AdafruitDisplay {
         Ok(display)
     }
 
-    /// Initializes the driver for the "Adafruit i2c 16x2 RGB LCD Pi Plate"
-    pub fn for_backplate() -> Result<Self, CommunicationError> {
-        AdafruitDisplay::new(16, 2, true, true, MCP230xx::for_mcp23017()?)
+    /// Setup the GPIO extender, and run the initialization sequence of the
+    /// LCD controller which leaves it cleared.
+    fn initialize(&mut self) -> Result<(), CommunicationError> {
+        self.gpio.setup(LCD_PLATE_RW, Mode::Output)?;
+        self.gpio.output(LCD_PLATE_RW, false)?;
+        for button in Button::ALL.iter() {
+            self.gpio.setup(*button as u8, Mode::Input)?;
+            self.gpio.pullup(*button as u8, PullUpDown::PullUp)?;
+        }
+
+        // Setup all pins as OUTPUT
+        for pin in &[self.rs, self.en, self.d4, self.d5, self.d6, self.d7] {
+            self.gpio.setup(*pin, Mode::Output)?;
+        }
+
+        self.write8(0x33, false)?;
+        self.write8(0x32, false)?;
+
+        let displaycontrol = LCD_DISPLAYCONTROL | self.displaycontrol;
+        let displayfunction = LCD_FUNCTIONSET | self.displayfunction;
+        let displaymode = LCD_ENTRYMODESET | self.displaymode;
+        self.write8(displaycontrol, false)?;
+        self.write8(displayfunction, false)?;
+        self.write8(displaymode, false)?;
+        self.clear()
+    }
+
+    /// Run the initialization sequence again, e.g. when the panel got garbled
+    /// by a glitch on its power supply, then restore the custom characters,
+    /// the backlight and the last frame that was shown.
+    pub fn reinit(&mut self) -> Result<(), CommunicationError> {
+        let frame = self.shadow.clone();
+        let shift = self.shift;
+        self.initialize()?;
+        for location in 0..cgram::LOCATIONS as u8 {
+            if let Some(pattern) = self.glyphs.get(location) {
+                self.write_cgram(location, pattern)?;
+            }
+        }
+        let (r, g, b) = self.color;
+        self.set_color(r, g, b)?;
+        self.show(&frame)?;
+        self.shift_to(shift)
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// A blank frame, matching the size of the panel.
+    pub fn frame(&self) -> Frame {
+        Frame::new(self.geometry.cols(), self.geometry.lines())
+    }
+
+    /// Show the given frame, only sending the cells that differ from what
+    /// the panel currently shows. The cursor is moved as little as possible:
+    /// characters are written sequentially, and an unchanged cell between two
+    /// changed ones is written again since it is as cheap as moving the cursor.
+    pub fn render(&mut self, frame: &Frame) -> Result<(), CommunicationError> {
+        self.shift_to(0)?;
+        // The columns hidden past the edge of the panel are left as they are.
+        let codes = self.encode_frame(frame, self.geometry.cols())?;
+        self.show(&codes)
+    }
+
+    /// Show the given frame, which can be wider than the panel, from the
+    /// column `offset`. Panels of 1 or 2 lines keep up to 40 columns in
+    /// their memory, so frames that fit are written once, then the whole
+    /// display is shifted in hardware, which only takes one instruction per
+    /// column. Otherwise the visible part of the frame is rendered.
+    pub fn render_scrolled(&mut self, frame: &Frame, offset: u8) -> Result<(), CommunicationError> {
+        if frame.cols() > self.shadow.cols() || self.shadow.cols() == self.geometry.cols() {
+            let mut window = self.frame();
+            for line in 0..self.geometry.lines() {
+                for col in 0..self.geometry.cols() {
+                    let c = frame.get(col.saturating_add(offset), line).unwrap_or(' ');
+                    window.set(col, line, c);
+                }
+            }
+            return self.render(&window);
+        }
+        let codes = self.encode_frame(frame, self.shadow.cols())?;
+        self.show(&codes)?;
+        self.shift_to(offset)
+    }
+
+    /// Load the glyphs needed by the first `cols` columns of the frame, and
+    /// turn them into character codes. The other columns are taken from the
+    /// shadow.
+    fn encode_frame(&mut self, frame: &Frame, cols: u8) -> Result<Frame, CommunicationError> {
+        // Loading glyphs moves the address counter to CGRAM, so it's done first.
+        let lines = self.geometry.lines();
+        let chars = (0..lines)
+            .flat_map(|line| (0..cols).map(move |col| (col, line)))
+            .map(|(col, line)| frame.get(col, line).unwrap_or(' '));
+        self.load_glyphs(chars, [false; cgram::LOCATIONS])?;
+        let mut codes = self.shadow.clone();
+        for line in 0..self.geometry.lines() {
+            for col in 0..cols {
+                let code = self.encode(frame.get(col, line).unwrap_or(' '));
+                codes.set(col, line, char::from(code));
+            }
+        }
+        Ok(codes)
+    }
+
+    /// Shift the display so that the given column of DDRAM shows on the
+    /// first column of the panel, going the shortest way around.
+    fn shift_to(&mut self, offset: u8) -> Result<(), CommunicationError> {
+        let length = self.geometry.line_length();
+        let left = (offset % length + length - self.shift) % length;
+        let (steps, direction) = if left <= length / 2 {
+            (left, LCD_MOVELEFT)
+        } else {
+            (length - left, LCD_MOVERIGHT)
+        };
+        for _ in 0..steps {
+            self.write8(LCD_CURSORSHIFT | LCD_DISPLAYMOVE | direction, false)?;
+        }
+        self.shift = offset % length;
+        Ok(())
+    }
+
+    /// Send the cells of a frame of character codes that differ from the shadow.
+    fn show(&mut self, codes: &Frame) -> Result<(), CommunicationError> {
+        for line in 0..self.geometry.lines() {
+            for col in 0..self.shadow.cols() {
+                let code = codes.get(col, line).unwrap_or(' ');
+                if self.shadow.get(col, line) == Some(code) {
+                    continue;
+                }
+                match self.cursor {
+                    Some((c, l)) if (c, l) == (col, line) => {}
+                    Some((c, l)) if self.next_cell(c, l) == Some((col, line)) => {
+                        let unchanged = self.shadow.get(c, l).unwrap_or(' ');
+                        self.put(unchanged as u8)?;
+                    }
+                    _ => self.set_cursor(col, line)?,
+                }
+                self.put(code as u8)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Select the character generator ROM the panel was manufactured with,
+    /// `CharacterRom::A00` by default. Text already shown isn't updated.
+    pub fn set_character_rom(&mut self, rom: CharacterRom) {
+        self.rom = rom;
+    }
+
+    /// Register a custom character under the given name, or update its
+    /// pattern, and return the character standing for it in frames. A
+    /// CGRAM location is only handed out when a frame showing it is
+    /// rendered, the least recently used glyphs making room for new ones.
+    pub fn define_glyph(&mut self, name: &str, pattern: [u8; 8]) -> char {
+        self.glyphs.define(name, pattern)
+    }
+
+    /// Load the glyphs needed to show the given characters in CGRAM,
+    /// without evicting the `pinned` locations. Named glyphs must all fit,
+    /// while characters missing from the ROM only get a glyph if there's
+    /// room left.
+    fn load_glyphs(
+        &mut self,
+        chars: impl Iterator<Item = char>,
+        mut pinned: [bool; cgram::LOCATIONS],
+    ) -> Result<(), CommunicationError> {
+        let (mut required, mut optional) = (vec![], vec![]);
+        for c in chars {
+            if (c as usize) < cgram::LOCATIONS {
+                pinned[c as usize] = true;
+            } else if self.glyphs.is_named(c) {
+                if !required.contains(&c) {
+                    required.push(c);
+                }
+            } else if self.rom.encode(c).is_none()
+                && charset::glyph(c).is_some()
+                && !optional.contains(&c)
+            {
+                optional.push(c);
+            }
+        }
+        let loads = self
+            .glyphs
+            .allocate(&required, &optional, pinned)
+            .map_err(CommunicationError::TooManyGlyphs)?;
+        for (location, _, pattern) in loads {
+            self.write_cgram(location, pattern)?;
+        }
+        Ok(())
+    }
+
+    /// Character code showing the given character, once its glyph was
+    /// loaded if it needs one. Characters missing from the ROM without a
+    /// glyph are replaced by a look-alike, or `?`.
+    fn encode(&self, c: char) -> u8 {
+        if (c as u32) < 0x10 {
+            return c as u8;
+        }
+        if let Some(code) = self.rom.encode(c) {
+            return code;
+        }
+        if let Some(location) = self.glyphs.location(c) {
+            return location;
+        }
+        charset::transliterate(c)
+            .and_then(|c| self.rom.encode(c))
+            .unwrap_or(b'?')
+    }
+
+    /// Initializes the driver for the "Adafruit i2c 16x2 RGB LCD Pi Plate",
+    /// using the given GPIO extender.
+    pub fn for_backplate_with(gpio: MCP230xx<B>) -> Result<Self, CommunicationError> {
+        DisplayBuilder::new(Geometry::LCD_16X2).build(gpio)
     }
 
     /// Write 8-bit value in character or data mode. Value should be an int
@@ -221,6 +438,7 @@ impl AdafruitDisplay {
     /// will turn it off.
     pub fn set_color(&mut self, r: u8, g: u8, b: u8) -> Result<(), CommunicationError> {
         // TODO: implement PWM
+        self.color = (r, g, b);
         self.gpio.setup(LCD_PLATE_RED, Mode::Output)?;
         self.gpio.setup(LCD_PLATE_GREEN, Mode::Output)?;
         self.gpio.setup(LCD_PLATE_BLUE, Mode::Output)?;
@@ -250,10 +468,20 @@ impl AdafruitDisplay {
         Ok(self.backlight)
     }
 
+    /// Turn the backlight on, in white, or off.
+    pub fn set_backlight(&mut self, on: bool) -> Result<(), CommunicationError> {
+        if on != self.backlight {
+            self.toggle_backlight()?;
+        }
+        Ok(())
+    }
+
     /// Move the cursor back to its start point (upper-left corner).
     pub fn home(&mut self) -> Result<(), CommunicationError> {
         self.write8(LCD_RETURNHOME, false)?;
         helpers::delay_microseconds(3000);
+        self.shift = 0;
+        self.cursor = Some((0, 0));
         Ok(())
     }
 
@@ -290,39 +518,115 @@ impl AdafruitDisplay {
         pattern: [u8; 8],
     ) -> Result<char, CommunicationError> {
         location &= 0x7;
+        self.glyphs.load(location, char::from(location), pattern);
+        self.write_cgram(location, pattern)?;
+        Ok(char::from_u32(location as u32).unwrap())
+    }
+
+    fn write_cgram(&mut self, location: u8, pattern: [u8; 8]) -> Result<(), CommunicationError> {
+        // The address counter now points to CGRAM.
+        self.cursor = None;
         self.write8(LCD_SETCGRAMADDR | (location << 3), false)?;
-        for i in 0..8 {
-            self.write8(pattern[i], true)?;
+        for line in pattern.iter() {
+            self.write8(*line, true)?;
         }
-        Ok(char::from_u32(location as u32).unwrap())
+        Ok(())
     }
 
     /// Write text to display. Note that text can include newlines.
     pub fn message(&mut self, text: &str) -> Result<(), CommunicationError> {
-        let mut line = 0;
+        let cursor = self.cursor;
+        // Glyphs still visible around the text are kept.
+        let pinned = self.glyphs.refs().map(|refs| refs > 0);
+        self.load_glyphs(text.chars(), pinned)?;
+        // Loading glyphs moved the address counter to CGRAM.
+        if self.cursor != cursor {
+            if let Some((col, line)) = cursor {
+                self.set_cursor(col, line)?;
+            }
+        }
+        let left_to_right = self.displaymode & LCD_ENTRYLEFT > 0;
+        let first_col = if left_to_right {
+            0
+        } else {
+            self.geometry.cols() - 1
+        };
+        let mut line = self.cursor.map_or(0, |(_, line)| line);
+        let mut wrap = false;
         for c in text.chars() {
-            let col = if self.displaymode & LCD_ENTRYLEFT > 0 {
-                0
-            } else {
-                self.cols - 1
-            };
-            if c == '\n' {
+            // Lines wrap at the edge of the panel, text past the last line is dropped.
+            if c == '\n' || wrap {
                 line += 1;
-                self.set_cursor(col, line)?;
-            } else {
-                self.write8(c as u8, true)?;
+                if line >= self.geometry.lines() {
+                    break;
+                }
+                self.set_cursor(first_col, line)?;
+            }
+            wrap = false;
+            if c != '\n' {
+                let cell = self.cursor;
+                let code = self.encode(c);
+                self.put(code)?;
+                wrap = left_to_right
+                    && cell.is_some_and(|(col, _)| col + 1 >= self.geometry.cols());
             }
         }
         Ok(())
     }
 
-    /// Move the cursor to an explicit column and row position.
-    pub fn set_cursor(&mut self, col: u8, mut line: u8) -> Result<(), CommunicationError> {
-        if line > self.lines {
-            line = self.lines - 1;
+    /// Write a character code at the cursor, keeping track of what the panel shows.
+    fn put(&mut self, code: u8) -> Result<(), CommunicationError> {
+        self.write8(code, true)?;
+        if self.displaymode & LCD_ENTRYSHIFTINCREMENT > 0 {
+            let length = self.geometry.line_length();
+            self.shift = if self.displaymode & LCD_ENTRYLEFT > 0 {
+                (self.shift + 1) % length
+            } else {
+                (self.shift + length - 1) % length
+            };
+        }
+        if let Some((col, line)) = self.cursor {
+            let old = self.shadow.get(col, line).unwrap_or(' ');
+            self.glyphs.replace(old as u8, code);
+            self.shadow.set(col, line, char::from(code));
+            self.cursor = if self.displaymode & LCD_ENTRYSHIFTINCREMENT > 0 {
+                // The whole display moved.
+                None
+            } else {
+                self.next_cell(col, line)
+            };
         }
-        let row = [0x00, 0x40, 0x14, 0x54][line as usize];
-        self.write8(LCD_SETDDRAMADDR | (col + row), false)
+        Ok(())
+    }
+
+    /// The cell the address counter moves to after writing the given one,
+    /// `None` if it leaves the memory of the line or the panel.
+    fn next_cell(&self, col: u8, line: u8) -> Option<(u8, u8)> {
+        let next = if self.displaymode & LCD_ENTRYLEFT > 0 {
+            (col.checked_add(1)?, line)
+        } else {
+            (col.checked_sub(1)?, line)
+        };
+        let address = self.geometry.address(col, line)?;
+        let next_address = self.geometry.address(next.0, next.1)?;
+        let moved = if self.displaymode & LCD_ENTRYLEFT > 0 {
+            address.checked_add(1)
+        } else {
+            address.checked_sub(1)
+        };
+        Some(next).filter(|_| moved == Some(next_address))
+    }
+
+    /// Move the cursor to an explicit column and row position. Columns past
+    /// the edge of the panel can be used when it keeps them in memory, see
+    /// `Geometry::memory_cols`.
+    pub fn set_cursor(&mut self, col: u8, line: u8) -> Result<(), CommunicationError> {
+        let address = self
+            .geometry
+            .address(col, line)
+            .ok_or(CommunicationError::CursorOutOfRange(col, line))?;
+        self.cursor = Some((col, line));
+        self.write8(LCD_SETDDRAMADDR | address, false)
     }
 
     /// Pulse the clock enable line off, on, off to send command.
@@ -340,6 +644,10 @@ impl AdafruitDisplay {
     pub fn clear(&mut self) -> Result<(), CommunicationError> {
         self.write8(LCD_CLEARDISPLAY, false)?;
         helpers::delay_microseconds(3000); // 3000 microsecond sleep, clearing the display takes a long time
+        self.shadow.clear();
+        self.glyphs.clear_refs();
+        self.shift = 0;
+        self.cursor = Some((0, 0));
         Ok(())
     }
 
@@ -347,4 +655,247 @@ impl AdafruitDisplay {
     pub fn is_pressed(&mut self, button: Button) -> Result<bool, CommunicationError> {
         Ok(self.gpio.input(button as u8)? == Level::Low)
     }
+
+    /// Read all the buttons at once, indexed by `Button`, `true` meaning pressed.
+    pub fn pressed_buttons(&mut self) -> Result<[bool; 5], CommunicationError> {
+        let pins: Vec<u8> = Button::ALL.iter().map(|b| *b as u8).collect();
+        let mut pressed = [false; 5];
+        for (i, level) in self.gpio.input_pins(&pins)?.iter().enumerate() {
+            pressed[i] = *level == Level::Low;
+        }
+        Ok(pressed)
+    }
+
+    /// Make the MCP23017 raise an interrupt whenever a button changes. The
+    /// INTA and INTB lines are mirrored and open-drain, active low.
+    pub fn enable_button_interrupts(&mut self) -> Result<(), CommunicationError> {
+        self.gpio.configure_interrupt_output(true, true, false)?;
+        for button in Button::ALL.iter() {
+            self.gpio
+                .enable_interrupt(*button as u8, Interrupt::OnChange)?;
+        }
+        Ok(())
+    }
+
+    /// Return the buttons as they were when the pending interrupt was
+    /// raised, and clear it. `None` if no button raised an interrupt.
+    pub fn button_interrupt(&mut self) -> Result<Option<[bool; 5]>, CommunicationError> {
+        let flags = self.gpio.interrupt_flags()?;
+        if !Button::ALL.iter().any(|b| flags.contains(&(*b as u8))) {
+            return Ok(None);
+        }
+        let pins: Vec<u8> = Button::ALL.iter().map(|b| *b as u8).collect();
+        let mut pressed = [false; 5];
+        for (i, level) in self.gpio.interrupt_capture(&pins)?.iter().enumerate() {
+            pressed[i] = *level == Level::Low;
+        }
+        Ok(Some(pressed))
+    }
+}
+
+/// Lets `write!` send text to the panel, like `message`.
+impl<B: I2cBus> fmt::Write for AdafruitDisplay<B> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.message(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl AdafruitDisplay<I2c> {
+    /// Initializes the driver for the "Adafruit i2c 16x2 RGB LCD Pi Plate"
+    pub fn for_backplate() -> Result<Self, CommunicationError> {
+        AdafruitDisplay::for_backplate_with(MCP230xx::for_mcp23017()?)
+    }
+}
+
+#[test]
+fn buttons() {
+    use crate::i2c::bus::mock::MockBus;
+
+    let bus = MockBus::new();
+    let mut display =
+        AdafruitDisplay::for_backplate_with(MCP230xx::mcp23017_with_bus(bus.clone()).unwrap())
+            .unwrap();
+
+    // Buttons are pulled up, and read low when pressed.
+    bus.set_registers(0x20, 0x12, &[0x1E]);
+    assert!(display.is_pressed(Button::Select).unwrap());
+    assert!(!display.is_pressed(Button::Left).unwrap());
+}
+
+#[test]
+fn render() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let mut frame = display.frame();
+    frame.write(0, 0, "Blocked 123 ads\n12.5% less junk");
+    display.render(&frame).unwrap();
+    assert_eq!(plate.text(), ["Blocked 123 ads ", "12.5% less junk "]);
+
+    // Only the changed cells are sent: "124" and "6" with a single cursor move each,
+    // and the unchanged "." between "2" and "6" is written again.
+    let count = plate.lcd().instruction_count();
+    frame.write(8, 0, "124");
+    frame.write(1, 1, "6.6");
+    display.render(&frame).unwrap();
+    assert_eq!(plate.text(), ["Blocked 124 ads ", "16.6% less junk "]);
+    assert_eq!(plate.lcd().instruction_count() - count, 1 + 1 + 1 + 3);
+    assert!(plate.errors().is_empty());
+
+    // Nothing to do when the frame is already shown.
+    let count = plate.lcd().instruction_count();
+    display.render(&frame).unwrap();
+    assert_eq!(plate.lcd().instruction_count(), count);
+}
+
+#[test]
+fn reinit() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let c = display.create_char(1, [0x1F; 8]).unwrap();
+    let mut frame = display.frame();
+    frame.write(0, 1, &format!("{}ok", c));
+    display.render(&frame).unwrap();
+    display.set_color(255, 0, 0).unwrap();
+
+    display.reinit().unwrap();
+    assert_eq!(plate.text(), ["                ", "\u{1}ok             "]);
+    assert_eq!(plate.lcd().glyph(1), [0x1F; 8]);
+    assert_eq!(plate.backlight(), (true, false, false));
+}
+
+#[test]
+fn character_rom() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let mut frame = display.frame();
+    frame.write(0, 0, "21°C café");
+    frame.write(0, 1, "Très “bien”");
+    display.render(&frame).unwrap();
+    // The A00 ROM has the degree sign, but no accented letters.
+    assert_eq!(
+        plate.text(),
+        ["21\u{df}C caf\u{0}       ", "Tr\u{1}s \"bien\"     "]
+    );
+    assert_eq!(plate.lcd().glyph(0), charset::glyph('é').unwrap());
+    assert!(plate.errors().is_empty());
+
+    // Without any free CGRAM location, a look-alike is used.
+    let bars: String = (1..7)
+        .map(|i| display.define_glyph(&format!("bar{}", i), [0x1F >> i; 8]))
+        .collect();
+    frame.write(5, 1, &format!("{}     ", bars));
+    display.render(&frame).unwrap();
+    display.set_cursor(0, 0).unwrap();
+    display.message("ça").unwrap();
+    assert_eq!(plate.text()[0], "ca\u{df}C caf\u{0}       ");
+
+    display.set_character_rom(CharacterRom::A02);
+    frame.write(0, 0, "ça");
+    display.render(&frame).unwrap();
+    assert_eq!(plate.text()[0], "\u{e7}a\u{b0}C caf\u{e9}       ");
+}
+
+#[test]
+fn glyph_cache() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let ferris = helpers::load_ferris(&mut display);
+    let mut frame = display.frame();
+    frame.write(0, 0, &ferris.iter().collect::<String>());
+    display.render(&frame).unwrap();
+    assert_eq!(plate.lcd().glyph(5), [0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0xF]);
+
+    // Glyphs that are still loaded aren't sent again.
+    let full = display.define_glyph("full", [0x1F; 8]);
+    let empty = display.define_glyph("empty", [0; 8]);
+    frame.clear();
+    frame.write(0, 0, &format!("{}{}{}", ferris[0], full, empty));
+    display.render(&frame).unwrap();
+    assert_eq!(plate.text()[0], "\u{0}\u{1}\u{2}             ");
+    assert_eq!(plate.lcd().glyph(1), [0x1F; 8]);
+
+    // The least recently used glyphs are evicted first: the bottom of Ferris.
+    frame.write(0, 0, &format!("{}{}{}{}{}   ", ferris[0], ferris[1], ferris[2], ferris[3], full));
+    display.render(&frame).unwrap();
+    assert_eq!(plate.text()[0], "\u{0}\u{4}\u{5}\u{3}\u{1}           ");
+    assert_eq!(plate.lcd().glyph(2), [0; 8]);
+
+    // Glyphs visible around a message are kept.
+    let more: Vec<char> = (0..4)
+        .map(|i| display.define_glyph(&format!("more{}", i), [i; 8]))
+        .collect();
+    display.set_cursor(8, 0).unwrap();
+    display.message(&more[..3].iter().collect::<String>()).unwrap();
+    assert_eq!(plate.text()[0], "\u{0}\u{4}\u{5}\u{3}\u{1}   \u{6}\u{7}\u{2}     ");
+    match display.message(&more[3].to_string()) {
+        Err(CommunicationError::TooManyGlyphs(9)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    // A frame can't show more than 8 different glyphs.
+    frame.write(8, 0, &more.iter().collect::<String>());
+    match display.render(&frame) {
+        Err(CommunicationError::TooManyGlyphs(9)) => {}
+        other => panic!("{:?}", other),
+    }
+    assert!(plate.errors().is_empty());
+}
+
+#[test]
+fn geometries() {
+    use crate::i2c::lcd::emulator::VirtualBackplate;
+
+    let plate = VirtualBackplate::new(20, 4);
+    let mut display = DisplayBuilder::new(Geometry::LCD_20X4)
+        .build(MCP230xx::mcp23017_with_bus(plate.clone()).unwrap())
+        .unwrap();
+    display
+        .message("Queries    123456\nBlocked: the first line wraps")
+        .unwrap();
+    assert_eq!(
+        plate.text(),
+        [
+            "Queries    123456   ",
+            "Blocked: the first l",
+            "ine wraps           ",
+            "                    "
+        ]
+    );
+    match display.set_cursor(0, 4) {
+        Err(CommunicationError::CursorOutOfRange(0, 4)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    let mut frame = display.frame();
+    frame.write(0, 3, "Ads 12.5%");
+    display.render(&frame).unwrap();
+    assert_eq!(plate.text()[3], "Ads 12.5%           ");
+    assert!(plate.errors().is_empty());
+
+    let plate = VirtualBackplate::new(16, 1);
+    let mut display = DisplayBuilder::new(Geometry::LCD_16X1)
+        .build(MCP230xx::mcp23017_with_bus(plate.clone()).unwrap())
+        .unwrap();
+    assert!(!plate.lcd().is_two_lines());
+    display.message("pi.hole is up\nand running").unwrap();
+    assert_eq!(plate.text(), ["pi.hole is up   "]);
+
+    // The right half of split panels is on the second line of memory.
+    let plate = VirtualBackplate::new(16, 1);
+    let mut display = DisplayBuilder::new(Geometry::LCD_16X1.split())
+        .build(MCP230xx::mcp23017_with_bus(plate.clone()).unwrap())
+        .unwrap();
+    let mut frame = display.frame();
+    frame.write(0, 0, "Blocked 12.5%");
+    display.render(&frame).unwrap();
+    assert_eq!(&plate.lcd().ddram()[..8], b"Blocked ");
+    assert_eq!(&plate.lcd().ddram()[0x40..0x48], b"12.5%   ");
 }
@@ -0,0 +1,184 @@
+use crate::i2c::bus::I2cBus;
+use crate::i2c::lcd::{AdafruitDisplay, Frame};
+
+/// Pixel rows in a character cell.
+const CELL_HEIGHT: u32 = 8;
+
+/// A series drawn as vertical bars, one per column, 8 pixels tall per
+/// row. Levels between empty and full cells are custom characters, so a
+/// sparkline takes up to 7 CGRAM locations, which are shared between all
+/// the sparklines of a frame.
+///
+/// The sparkline is laid out from `col` and `line`, its last row being
+/// the bottom one, taking `width` characters along with its label, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sparkline {
+    col: u8,
+    line: u8,
+    width: u8,
+    rows: u8,
+    label: Option<String>,
+    max: Option<f32>,
+}
+
+impl Sparkline {
+    pub fn new(col: u8, line: u8, width: u8) -> Self {
+        Sparkline {
+            col,
+            line,
+            width,
+            rows: 1,
+            label: None,
+            max: None,
+        }
+    }
+
+    /// Draw the bars on 2 rows, for twice the resolution.
+    pub fn rows(mut self, rows: u8) -> Self {
+        self.rows = rows.clamp(1, 2);
+        self
+    }
+
+    /// Text shown on the bottom row before the bars, followed by a space.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Value of a full bar, the largest value of the series by default.
+    /// Setting it lets several sparklines share the same scale.
+    pub fn max(mut self, max: f32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Number of columns left for the bars.
+    pub fn bars_width(&self) -> u8 {
+        let label = self
+            .label
+            .as_ref()
+            .map_or(0, |label| label.chars().count() + 1);
+        (self.width as usize).saturating_sub(label) as u8
+    }
+
+    /// Draw the series, the oldest value first, downsampled to the width of
+    /// the sparkline. Shorter series are aligned to the right.
+    pub fn draw<B: I2cBus>(
+        &self,
+        display: &mut AdafruitDisplay<B>,
+        frame: &mut Frame,
+        values: &[f32],
+    ) {
+        let width = self.bars_width();
+        let values = downsample(values, width as usize);
+        let max = self
+            .max
+            .unwrap_or_else(|| values.iter().cloned().fold(0.0, f32::max));
+        let pixels = u32::from(self.rows) * CELL_HEIGHT;
+
+        let mut rows = vec![String::new(); self.rows as usize];
+        let padding = width as usize - values.len();
+        for row in rows.iter_mut() {
+            if let Some(ref label) = self.label {
+                row.push_str(&" ".repeat(label.chars().count() + 1));
+            }
+            row.push_str(&" ".repeat(padding));
+        }
+        if let (Some(label), Some(bottom)) = (&self.label, rows.last_mut()) {
+            // The padding is made of single byte spaces.
+            bottom.replace_range(..label.chars().count(), label);
+        }
+
+        for value in values {
+            let level = if max > 0.0 && value > 0.0 {
+                // Any non-zero value shows up.
+                ((value / max).min(1.0) * pixels as f32).round().max(1.0) as u32
+            } else {
+                0
+            };
+            for (i, row) in rows.iter_mut().enumerate() {
+                let from_bottom = (self.rows as usize - 1 - i) as u32;
+                let height = level
+                    .saturating_sub(from_bottom * CELL_HEIGHT)
+                    .min(CELL_HEIGHT);
+                row.push(match height {
+                    0 => ' ',
+                    CELL_HEIGHT => '█',
+                    height => {
+                        let mut pattern = [0; 8];
+                        for line in pattern.iter_mut().skip((CELL_HEIGHT - height) as usize) {
+                            *line = 0x1F;
+                        }
+                        display.define_glyph(&format!("sparkline{}", height), pattern)
+                    }
+                });
+            }
+        }
+
+        for (i, row) in rows.iter().enumerate() {
+            frame.write(self.col, self.line.saturating_add(i as u8), row);
+        }
+    }
+}
+
+/// Shrink the series to at most `width` values, each one being the average
+/// of the values it stands for.
+pub fn downsample(values: &[f32], width: usize) -> Vec<f32> {
+    if values.len() <= width {
+        return values.to_vec();
+    }
+    (0..width)
+        .map(|i| {
+            let bucket = &values[i * values.len() / width..(i + 1) * values.len() / width];
+            bucket.iter().sum::<f32>() / bucket.len() as f32
+        })
+        .collect()
+}
+
+#[test]
+fn downsampling() {
+    assert_eq!(downsample(&[1.0, 2.0], 4), [1.0, 2.0]);
+    assert_eq!(
+        downsample(&[1.0, 3.0, 2.0, 2.0, 0.0, 6.0], 3),
+        [2.0, 2.0, 3.0]
+    );
+    assert_eq!(downsample(&[1.0, 2.0, 3.0, 4.0, 5.0], 2), [1.5, 4.0]);
+}
+
+#[test]
+fn draw() {
+    use crate::i2c::lcd::emulator::{self, VirtualBackplate};
+
+    let plate = VirtualBackplate::for_backplate();
+    let mut display = emulator::display(&plate);
+    let mut frame = display.frame();
+    Sparkline::new(0, 0, 6)
+        .label("Q")
+        .draw(&mut display, &mut frame, &[0.0, 2.0, 4.0, 8.0]);
+    Sparkline::new(8, 0, 4).rows(2).max(16.0).draw(
+        &mut display,
+        &mut frame,
+        &[4.0, 12.0, 20.0, 0.1],
+    );
+    display.render(&frame).unwrap();
+
+    let text: Vec<Vec<char>> = plate.text().iter().map(|l| l.chars().collect()).collect();
+    let lcd = plate.lcd();
+    let height = |c: char| match c as u32 {
+        0..=7 => lcd
+            .glyph(c as u8)
+            .iter()
+            .filter(|line| **line == 0x1F)
+            .count(),
+        0xFF => 8,
+        _ => 0,
+    };
+    assert_eq!(&text[0][..2], ['Q', ' ']);
+    let heights = |line: usize, cols: std::ops::Range<usize>| -> Vec<usize> {
+        text[line][cols].iter().map(|c| height(*c)).collect()
+    };
+    assert_eq!(heights(0, 2..6), [0, 2, 4, 8]);
+    assert_eq!(heights(0, 8..12), [0, 4, 8, 0]);
+    assert_eq!(heights(1, 8..12), [4, 8, 8, 1]);
+    assert!(plate.errors().is_empty());
+}
@@ -3,6 +3,7 @@ use rppal::i2c::I2c;
 use std::path::PathBuf;
 
 use crate::errors::CommunicationError;
+use crate::i2c::bus::{self, DefaultBus, I2cBus};
 
 const HT16K33_BLINK_CMD: u8 = 0x80;
 const HT16K33_SYSTEM_SETUP: u8 = 0x20;
@@ -30,8 +31,8 @@ pub trait BicolorMatrix8x8 {
     fn clear(&mut self) -> Result<(), CommunicationError>;
 }
 
-pub struct HT16K33 {
-    device: I2c,
+pub struct HT16K33<B: I2cBus = I2c> {
+    device: B,
     buffer: [u8; 16],
 }
 
@@ -70,7 +71,29 @@ pub enum Bit {
     Off = 0x00,
 }
 
-impl HT16K33 {
+impl HT16K33<I2c> {
+    /// Set up the LED backpack on the I2C bus of the Raspberry Pi.
+    pub fn new(brightness: u8, blink: Blink) -> Result<Self, CommunicationError> {
+        Self::with_bus(bus::open()?, brightness, blink)
+    }
+}
+
+impl<B: I2cBus> HT16K33<B> {
+    /// Set up the LED backpack found at the default 0x70 address on the given I2C bus.
+    pub fn with_bus(mut device: B, brightness: u8, blink: Blink) -> Result<Self, CommunicationError> {
+        device.set_slave_address(0x70)?;
+
+        let driver = HT16K33 {
+            device,
+            buffer: [0; 16],
+        };
+        driver.system_setup(Bit::On)?;
+        driver.brightness(brightness)?;
+        driver.blink(blink)?;
+
+        Ok(driver)
+    }
+
     /// The system setup register configures system operation or standby
     /// * The internal system oscillator is enabled when the 'S' bit of the system setup register is set to "1".
     /// * The internal system clock is disabled and the device will enter the standby mode when the "S" bit
@@ -78,7 +101,6 @@ impl HT16K33 {
     pub fn system_setup(&self, operation: Bit) -> Result<(), CommunicationError> {
         self.device
             .smbus_send_byte(HT16K33_SYSTEM_SETUP | operation as u8)
-            .map_err(|e| CommunicationError::BusError(e))
     }
 
     /// The display setup register configures the LED display on/off and the blinking frequency for the HT16K33.
@@ -87,8 +109,6 @@ impl HT16K33 {
     pub fn display_setup(&self, display: Bit, frequency: Blink) -> Result<(), CommunicationError> {
         self.device
             .block_write(HT16K33_BLINK_CMD | display as u8 | frequency as u8, &[])
-            .map_err(|e| CommunicationError::BusError(e))?;
-        Ok(())
     }
 
     pub fn set_led(&mut self, led: u8, value: u8, update: bool) -> Result<(), CommunicationError> {
@@ -103,54 +123,45 @@ impl HT16K33 {
             self.buffer[pos] |= 1 << offset
         }
         if update {
-            self.device
-                .block_write(pos as u8, &[self.buffer[pos]])
-                .map_err(|e| CommunicationError::BusError(e))?;
+            self.device.block_write(pos as u8, &[self.buffer[pos]])?;
         }
         Ok(())
     }
 
+    pub fn blink(&self, frequency: Blink) -> Result<&Self, CommunicationError> {
+        self.display_setup(Bit::On, frequency)?;
+        Ok(self)
+    }
+
+    pub fn brightness(&self, brightness: u8) -> Result<&Self, CommunicationError> {
+        if brightness >= 16 {
+            panic!("Brightness can't be more than 15");
+        }
+        self.device
+            .block_write(HT16K33_CMD_BRIGHTNESS | brightness, &[])?;
+        Ok(self)
+    }
+
     pub fn write_display(&self) -> Result<(), CommunicationError> {
         for (i, value) in self.buffer.iter().enumerate() {
-            self.device
-                .block_write(i as u8, &[*value])
-                .map_err(|e| CommunicationError::BusError(e))?;
+            self.device.block_write(i as u8, &[*value])?;
         }
         Ok(())
     }
 }
 
-impl BicolorMatrix8x8 for HT16K33 {
+impl<B: DefaultBus> BicolorMatrix8x8 for HT16K33<B> {
+    /// Set up the LED backpack on the default bus of its kind.
     fn new(brightness: u8, blink: Blink) -> Result<Self, CommunicationError> {
-        let mut device = I2c::new().unwrap();
-        device
-            .set_slave_address(0x70 as u16)
-            .map_err(|e| return CommunicationError::BusError(e))?;
-
-        let driver = HT16K33 {
-            device,
-            buffer: [0; 16],
-        };
-        driver.system_setup(Bit::On)?;
-        driver.brightness(brightness)?;
-        driver.blink(blink)?;
-
-        Ok(driver)
+        Self::with_bus(B::open()?, brightness, blink)
     }
 
     fn blink(&self, frequency: Blink) -> Result<&Self, CommunicationError> {
-        self.display_setup(Bit::On, frequency)?;
-        Ok(self)
+        HT16K33::blink(self, frequency)
     }
 
     fn brightness(&self, brightness: u8) -> Result<&Self, CommunicationError> {
-        if brightness >= 16 {
-            panic!("Brightness can't be more than 15");
-        }
-        self.device
-            .block_write(HT16K33_CMD_BRIGHTNESS | brightness, &[])
-            .map_err(|e| CommunicationError::BusError(e))?;
-        Ok(self)
+        HT16K33::brightness(self, brightness)
     }
 
     fn set_pixel(
@@ -160,8 +171,8 @@ impl BicolorMatrix8x8 for HT16K33 {
         color: Color,
         write_display: bool,
     ) -> Result<(), CommunicationError> {
-        assert_eq!(x < 8, true);
-        assert_eq!(y < 8, true);
+        assert!(x < 8);
+        assert!(y < 8);
         let (led1, led2) = match color {
             Color::Green => (1, 0),
             Color::Red => (0, 1),
@@ -174,7 +185,7 @@ impl BicolorMatrix8x8 for HT16K33 {
     }
 
     fn set_image(&mut self, filepath: PathBuf) -> Result<(), CommunicationError> {
-        let img = bmp::open(filepath).map_err(|e| CommunicationError::BitmapError(e))?;
+        let img = bmp::open(filepath).map_err(CommunicationError::BitmapError)?;
         if img.get_height() != 8 || img.get_width() != 8 {
             panic!("You need to provide a 8x8 BMP sprite");
         }
@@ -193,3 +204,20 @@ impl BicolorMatrix8x8 for HT16K33 {
         self.write_display()
     }
 }
+
+#[test]
+fn set_pixel() {
+    use crate::i2c::bus::mock::MockBus;
+
+    let bus = MockBus::new();
+    let mut matrix = HT16K33::with_bus(bus.clone(), 15, Blink::Off).unwrap();
+    matrix.set_pixel(2, 1, Color::Yellow, false).unwrap();
+    matrix.write_display().unwrap();
+    assert_eq!(bus.registers(0x70)[2..4], [0x04, 0x04]);
+
+    // Built from the trait, the bus has to have a default to open.
+    fn build<T: BicolorMatrix8x8>() -> Result<T, CommunicationError> {
+        T::new(15, Blink::Off)
+    }
+    assert!(build::<HT16K33<MockBus>>().is_ok());
+}
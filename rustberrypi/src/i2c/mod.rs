@@ -1,3 +1,4 @@
+pub mod bus;
 pub mod io;
 pub mod lcd;
 pub mod led;
@@ -1,5 +1,5 @@
 use crate::errors::CommunicationError;
-use rppal::i2c::I2c;
+use crate::i2c::bus::{self, I2cBus};
 
 use std::{thread, time};
 
@@ -31,10 +31,12 @@ impl AM2320 {
     }
 
     pub fn read() -> Result<Self, CommunicationError> {
-        let mut device = I2c::new().map_err(|e| CommunicationError::BusError(e))?;
-        device
-            .set_slave_address(0x5c as u16)
-            .map_err(|e| return CommunicationError::BusError(e))?;
+        Self::read_from(&mut bus::open()?)
+    }
+
+    /// Take a reading from the sensor at the default 0x5c address on the given I2C bus.
+    pub fn read_from<B: I2cBus>(device: &mut B) -> Result<Self, CommunicationError> {
+        device.set_slave_address(0x5c)?;
 
         // wake AM2320 up, goes to sleep to not warm up and affect the humidity sensor
         // This write will fail as AM2320 won't ACK this write
@@ -59,7 +61,9 @@ impl AM2320 {
         // # Byte 6: CRC lsb byte
         // # Byte 7: CRC msb byte
         let mut data: Vec<u8> = vec![0; 8];
-        device.read(&mut data).unwrap();
+        device
+            .read(&mut data)
+            .map_err(|_| CommunicationError::ReadingError)?;
 
         if data[0] != 0x03 || data[1] != 0x04 {
             return Err(CommunicationError::ReadingError);
@@ -96,3 +100,17 @@ fn combine_bytes() {
     assert_eq!(AM2320::combine_bytes(0, 0), 0);
     // assert_eq!(AM2320::combine_bytes(0xC5, 0x01), 0xDB);
 }
+
+#[test]
+fn read_from() {
+    use crate::i2c::bus::mock::MockBus;
+
+    let mut bus = MockBus::new();
+    bus.queue_read(&[0x03, 0x04, 0x02, 0x36, 0x00, 0xDB, 0x51, 0x05]);
+    assert!(AM2320::read_from(&mut bus).is_err());
+
+    bus.queue_read(&[0x03, 0x04, 0x02, 0x36, 0x00, 0xDB, 0x50, 0x05]);
+    let reading = AM2320::read_from(&mut bus).unwrap();
+    assert_eq!(reading.temperature, 21.9);
+    assert_eq!(reading.humidity, 56.6);
+}